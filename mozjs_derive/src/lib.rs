@@ -0,0 +1,424 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! `#[derive(ToJSValConvertible, FromJSValConvertible)]` for the traits in
+//! `mozjs::conversions`.
+//!
+//! Structs with named fields convert to and from plain objects, like WebIDL
+//! dictionaries: each field is a member named after the field, or after
+//! `#[js(rename = "name")]`. When converting from JS, `undefined` members of
+//! `Option` fields become `None`, those of `#[js(default)]` fields become
+//! `Default::default()`, and those of other fields are a conversion failure.
+//! `None` fields are omitted when converting to JS. Field conversions use the
+//! default `Config` of the field type. Type parameters are required to
+//! implement the derived trait, with a default `Config` for
+//! `FromJSValConvertible`.
+//!
+//! Enums without fields convert to and from strings, like WebIDL enums: each
+//! variant is represented by its name, or by `#[js(rename = "name")]`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Fields, Generics, Lit, Meta, NestedMeta, Type};
+
+#[proc_macro_derive(ToJSValConvertible, attributes(js))]
+pub fn derive_to_jsval(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input, to_jsval_struct, to_jsval_enum)
+}
+
+#[proc_macro_derive(FromJSValConvertible, attributes(js))]
+pub fn derive_from_jsval(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input, from_jsval_struct, from_jsval_enum)
+}
+
+fn expand(
+    input: &DeriveInput,
+    for_struct: fn(&DeriveInput, &[Member]) -> TokenStream2,
+    for_enum: fn(&DeriveInput, &[Variant]) -> TokenStream2,
+) -> TokenStream {
+    let result = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => fields
+                .named
+                .iter()
+                .map(Member::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map(|members| for_struct(input, &members)),
+            _ => Err(Error::new(
+                input.span(),
+                "only structs with named fields can be converted",
+            )),
+        },
+        Data::Enum(ref data) => data
+            .variants
+            .iter()
+            .map(Variant::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|variants| for_enum(input, &variants)),
+        Data::Union(_) => Err(Error::new(input.span(), "unions cannot be converted")),
+    };
+    result
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// The generics of `input`, with the predicates returned by `bounds` added to
+/// the where clause for each type parameter.
+fn bounded_generics(
+    input: &DeriveInput,
+    bounds: fn(&syn::Ident) -> Vec<syn::WherePredicate>,
+) -> Generics {
+    let mut generics = input.generics.clone();
+    let params: Vec<_> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let where_clause = generics.make_where_clause();
+    for param in &params {
+        where_clause.predicates.extend(bounds(param));
+    }
+    generics
+}
+
+fn to_jsval_bounds(param: &syn::Ident) -> Vec<syn::WherePredicate> {
+    vec![syn::parse_quote! { #param: ::mozjs::conversions::ToJSValConvertible }]
+}
+
+/// Members are converted with the default `Config` of their type, so that has
+/// to exist for type parameters too.
+fn from_jsval_bounds(param: &syn::Ident) -> Vec<syn::WherePredicate> {
+    vec![
+        syn::parse_quote! { #param: ::mozjs::conversions::FromJSValConvertible },
+        syn::parse_quote! {
+            <#param as ::mozjs::conversions::FromJSValConvertible>::Config:
+                ::std::default::Default
+        },
+    ]
+}
+
+/// The options of a `#[js(...)]` attribute.
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    default: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[syn::Attribute], allow_default: bool) -> Result<Options, Error> {
+        let mut options = Options::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("js")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "expected #[js(...)]")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("rename") => {
+                        match pair.lit {
+                            Lit::Str(ref name) => options.rename = Some(name.value()),
+                            ref lit => return Err(Error::new(lit.span(), "expected a string")),
+                        }
+                    }
+                    NestedMeta::Meta(Meta::Path(ref path))
+                        if allow_default && path.is_ident("default") =>
+                    {
+                        options.default = true
+                    }
+                    nested => return Err(Error::new(nested.span(), "unknown js option")),
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+struct Member {
+    ident: syn::Ident,
+    ty: Type,
+    name: String,
+    default: bool,
+}
+
+impl Member {
+    fn parse(field: &syn::Field) -> Result<Member, Error> {
+        let options = Options::parse(&field.attrs, true)?;
+        let ident = field.ident.clone().unwrap();
+        Ok(Member {
+            name: options.rename.unwrap_or_else(|| ident.to_string()),
+            ident,
+            ty: field.ty.clone(),
+            default: options.default,
+        })
+    }
+
+    /// Whether the field has type `Option<_>`.
+    fn is_option(&self) -> bool {
+        match self.ty {
+            Type::Path(ref path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Option"),
+            _ => false,
+        }
+    }
+
+    /// The NUL-terminated member name as a byte string literal.
+    fn c_name(&self) -> syn::LitByteStr {
+        syn::LitByteStr::new(format!("{}\0", self.name).as_bytes(), Span::call_site())
+    }
+}
+
+struct Variant {
+    ident: syn::Ident,
+    name: String,
+}
+
+impl Variant {
+    fn parse(variant: &syn::Variant) -> Result<Variant, Error> {
+        match variant.fields {
+            Fields::Unit => {}
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "only enums without fields can be converted",
+                ))
+            }
+        }
+        let options = Options::parse(&variant.attrs, false)?;
+        Ok(Variant {
+            name: options.rename.unwrap_or_else(|| variant.ident.to_string()),
+            ident: variant.ident.clone(),
+        })
+    }
+}
+
+fn to_jsval_struct(input: &DeriveInput, members: &[Member]) -> TokenStream2 {
+    let name = &input.ident;
+    let generics = bounded_generics(input, to_jsval_bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let define_members = members.iter().map(|member| {
+        let ident = &member.ident;
+        let c_name = member.c_name();
+        let define = quote! {
+            let mut value_root = ::mozjs::jsapi::Rooted::new_unrooted();
+            let mut value = ::mozjs::rust::RootedGuard::new(
+                cx,
+                &mut value_root,
+                ::mozjs::jsval::UndefinedValue(),
+            );
+            ::mozjs::conversions::ToJSValConvertible::to_jsval(member, cx, value.handle_mut());
+            assert!(::mozjs::jsapi::JS_DefineProperty(
+                cx,
+                object.handle().into(),
+                #c_name.as_ptr() as *const ::std::os::raw::c_char,
+                value.handle().into(),
+                ::mozjs::jsapi::JSPROP_ENUMERATE as u32,
+            ));
+        };
+        if member.is_option() {
+            quote! {
+                if let Some(ref member) = self.#ident {
+                    #define
+                }
+            }
+        } else {
+            quote! {
+                {
+                    let member = &self.#ident;
+                    #define
+                }
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics ::mozjs::conversions::ToJSValConvertible for #name #ty_generics
+            #where_clause
+        {
+            unsafe fn to_jsval(
+                &self,
+                cx: *mut ::mozjs::jsapi::JSContext,
+                mut rval: ::mozjs::rust::MutableHandleValue,
+            ) {
+                let mut object_root = ::mozjs::jsapi::Rooted::new_unrooted();
+                let object = ::mozjs::rust::RootedGuard::new(
+                    cx,
+                    &mut object_root,
+                    ::mozjs::jsapi::JS_NewPlainObject(cx),
+                );
+                assert!(!object.is_null());
+                #(#define_members)*
+                rval.set(::mozjs::jsval::ObjectValue(object.get()));
+            }
+        }
+    }
+}
+
+fn from_jsval_struct(input: &DeriveInput, members: &[Member]) -> TokenStream2 {
+    let name = &input.ident;
+    let generics = bounded_generics(input, from_jsval_bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let read_members = members.iter().map(|member| {
+        let ident = &member.ident;
+        let ty = &member.ty;
+        let c_name = member.c_name();
+        let missing = if member.is_option() {
+            quote! { None }
+        } else if member.default {
+            quote! { ::std::default::Default::default() }
+        } else {
+            let message = format!("Missing required member \"{}\".", member.name);
+            quote! {
+                return Ok(::mozjs::conversions::ConversionResult::Failure(#message.into()))
+            }
+        };
+        let failure = format!("Invalid member \"{}\": ", member.name);
+        quote! {
+            let #ident: #ty = {
+                let mut value_root = ::mozjs::jsapi::Rooted::new_unrooted();
+                let mut value = ::mozjs::rust::RootedGuard::new(
+                    cx,
+                    &mut value_root,
+                    ::mozjs::jsval::UndefinedValue(),
+                );
+                if !object.is_null() && !::mozjs::jsapi::JS_GetProperty(
+                    cx,
+                    object.handle().into(),
+                    #c_name.as_ptr() as *const ::std::os::raw::c_char,
+                    value.handle_mut().into(),
+                ) {
+                    return Err(());
+                }
+                if value.is_undefined() {
+                    #missing
+                } else {
+                    match ::mozjs::conversions::FromJSValConvertible::from_jsval(
+                        cx,
+                        value.handle(),
+                        ::std::default::Default::default(),
+                    )? {
+                        ::mozjs::conversions::ConversionResult::Success(member) => member,
+                        ::mozjs::conversions::ConversionResult::Failure(error) => {
+                            return Ok(::mozjs::conversions::ConversionResult::Failure(
+                                format!("{}{}", #failure, error).into(),
+                            ));
+                        }
+                    }
+                }
+            };
+        }
+    });
+    let idents = members.iter().map(|member| &member.ident);
+    quote! {
+        impl #impl_generics ::mozjs::conversions::FromJSValConvertible for #name #ty_generics
+            #where_clause
+        {
+            type Config = ();
+
+            unsafe fn from_jsval(
+                cx: *mut ::mozjs::jsapi::JSContext,
+                value: ::mozjs::rust::HandleValue,
+                _option: (),
+            ) -> Result<::mozjs::conversions::ConversionResult<Self>, ()> {
+                // As with WebIDL dictionaries, null and undefined convert
+                // like an object without members.
+                let object = if value.is_object() {
+                    value.to_object()
+                } else if value.is_null_or_undefined() {
+                    ::std::ptr::null_mut()
+                } else {
+                    return Ok(::mozjs::conversions::ConversionResult::Failure(
+                        "Value is not an object".into(),
+                    ));
+                };
+                let mut object_root = ::mozjs::jsapi::Rooted::new_unrooted();
+                let object = ::mozjs::rust::RootedGuard::new(cx, &mut object_root, object);
+                #(#read_members)*
+                Ok(::mozjs::conversions::ConversionResult::Success(#name {
+                    #(#idents),*
+                }))
+            }
+        }
+    }
+}
+
+fn to_jsval_enum(input: &DeriveInput, variants: &[Variant]) -> TokenStream2 {
+    let name = &input.ident;
+    let generics = bounded_generics(input, to_jsval_bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let value = &variant.name;
+        quote! { #name::#ident => #value }
+    });
+    quote! {
+        impl #impl_generics ::mozjs::conversions::ToJSValConvertible for #name #ty_generics
+            #where_clause
+        {
+            unsafe fn to_jsval(
+                &self,
+                cx: *mut ::mozjs::jsapi::JSContext,
+                rval: ::mozjs::rust::MutableHandleValue,
+            ) {
+                let value: &str = match *self {
+                    #(#arms),*
+                };
+                ::mozjs::conversions::ToJSValConvertible::to_jsval(value, cx, rval);
+            }
+        }
+    }
+}
+
+fn from_jsval_enum(input: &DeriveInput, variants: &[Variant]) -> TokenStream2 {
+    let name = &input.ident;
+    let generics = bounded_generics(input, from_jsval_bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let value = &variant.name;
+        quote! { #value => #name::#ident }
+    });
+    let failure = format!(" is not a valid value for enumeration {}.", name);
+    quote! {
+        impl #impl_generics ::mozjs::conversions::FromJSValConvertible for #name #ty_generics
+            #where_clause
+        {
+            type Config = ();
+
+            unsafe fn from_jsval(
+                cx: *mut ::mozjs::jsapi::JSContext,
+                value: ::mozjs::rust::HandleValue,
+                _option: (),
+            ) -> Result<::mozjs::conversions::ConversionResult<Self>, ()> {
+                let string: String = match ::mozjs::conversions::FromJSValConvertible::from_jsval(
+                    cx,
+                    value,
+                    (),
+                )? {
+                    ::mozjs::conversions::ConversionResult::Success(string) => string,
+                    ::mozjs::conversions::ConversionResult::Failure(error) => {
+                        return Ok(::mozjs::conversions::ConversionResult::Failure(error));
+                    }
+                };
+                Ok(::mozjs::conversions::ConversionResult::Success(match &*string {
+                    #(#arms,)*
+                    _ => {
+                        return Ok(::mozjs::conversions::ConversionResult::Failure(
+                            format!("'{}'{}", string, #failure).into(),
+                        ));
+                    }
+                }))
+            }
+        }
+    }
+}
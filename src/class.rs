@@ -0,0 +1,404 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! JS classes whose instances hold Rust data.
+//!
+//! A class is described by a `NativeClass` implementation and registered on
+//! a global with a `ClassBuilder`. Each instance owns a boxed
+//! `NativeClass::Private`, which is traced along with the object and dropped
+//! when the object is finalized.
+//!
+//! ```ignore
+//! struct Counter;
+//!
+//! struct Count(Cell<i32>);
+//!
+//! unsafe impl Trace for Count {
+//!     unsafe fn trace(&self, _: *mut JSTracer) {}
+//! }
+//!
+//! impl NativeClass for Counter {
+//!     type Private = Count;
+//!     const NAME: &'static str = "Counter";
+//! }
+//!
+//! ClassBuilder::<Counter>::new()
+//!     .constructor(|start: i32| Ok(Count(Cell::new(start))))
+//!     .method("increment", |count: &Count| count.0.set(count.0.get() + 1))
+//!     .property("count", |count: &Count| count.0.get())
+//!     .init(cx, global.handle())?;
+//! ```
+//!
+//! Like with `define_function`, the constructor, methods and accessors must
+//! be function items or closures that capture nothing.
+
+use consts::JSCLASS_RESERVED_SLOTS_MASK;
+use conversions::FromJSValConvertible;
+use error::JsError;
+use function::zero_sized;
+use function::NativeClosure;
+use function::{argument, assert_zero_sized, call_native, check_arity, required_arguments};
+use glue::JS_GetReservedSlot;
+use jsapi::{jsid, CallArgs, JSClass, JSClassOps, JSContext, JSFreeOp, JSNative, JSObject};
+use jsapi::{JSTracer, JS_DefinePropertyById3, JS_DefineUCFunction, JS_InitClass};
+use jsapi::{JS_NewObjectForConstructor, JS_NewObjectWithGivenProto, JS_SetReservedSlot, Value};
+use jsapi::{JSCLASS_FOREGROUND_FINALIZE, JSCLASS_RESERVED_SLOTS_SHIFT};
+use jsval::{ObjectValue, PrivateValue, UndefinedValue};
+use panic::wrap_panic;
+use property_key::string_id;
+use rust::{get_object_class, Handle, HandleObject, MutableHandleValue, Trace};
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+/// A JS class implemented in Rust.
+pub trait NativeClass: 'static {
+    /// The Rust data owned by each instance.
+    type Private: Trace + 'static;
+
+    /// The name of the class, which is also the name of its constructor.
+    const NAME: &'static str;
+}
+
+/// A Rust function used as the constructor of a `NativeClass`, returning the
+/// private data of the new instance. See `NativeFunction`.
+pub trait NativeConstructor<P, Args>: 'static {
+    /// The number of arguments the constructor requires.
+    const ARITY: u32;
+
+    /// Converts the arguments and calls the constructor.
+    unsafe fn construct(&self, cx: *mut JSContext, args: &CallArgs) -> Result<P, JsError>;
+}
+
+macro_rules! impl_native_constructor {
+    ($($name:ident $arg:ident $index:expr),*) => {
+        impl<F, P, $($name,)*> NativeConstructor<P, ($($name,)*)> for F
+        where
+            F: Fn($($name),*) -> Result<P, JsError> + 'static,
+            $($name: FromJSValConvertible, $name::Config: Default,)*
+        {
+            const ARITY: u32 = required_arguments(&[$($name::OPTIONAL),*]);
+
+            unsafe fn construct(&self, cx: *mut JSContext, args: &CallArgs) -> Result<P, JsError> {
+                check_arity(args, required_arguments(&[$($name::OPTIONAL),*]))?;
+                $(let $arg = argument::<$name>(cx, args, $index)?;)*
+                self($($arg),*)
+            }
+        }
+    };
+}
+
+impl_native_constructor!();
+impl_native_constructor!(A a 0);
+impl_native_constructor!(A a 0, B b 1);
+impl_native_constructor!(A a 0, B b 1, C c 2);
+impl_native_constructor!(A a 0, B b 1, C c 2, D d 3);
+impl_native_constructor!(A a 0, B b 1, C c 2, D d 3, E e 4);
+impl_native_constructor!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5);
+impl_native_constructor!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5, H h 6);
+impl_native_constructor!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5, H h 6, I i 7);
+
+const PRIVATE_SLOT: u32 = 0;
+
+thread_local!(static CLASSES: RefCell<HashMap<TypeId, &'static JSClass>> =
+    RefCell::new(HashMap::new()));
+
+/// Returns the `JSClass` of `C`, which is created on first use and lives
+/// until the thread exits.
+///
+/// # Panics
+///
+/// Panics if the name of the class contains a NUL character.
+pub fn class_of<C: NativeClass>() -> &'static JSClass {
+    CLASSES.with(|classes| {
+        *classes
+            .borrow_mut()
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| {
+                let ops = Box::leak(Box::new(JSClassOps {
+                    addProperty: None,
+                    delProperty: None,
+                    enumerate: None,
+                    newEnumerate: None,
+                    resolve: None,
+                    mayResolve: None,
+                    finalize: Some(finalize::<C>),
+                    call: None,
+                    hasInstance: None,
+                    construct: None,
+                    trace: Some(trace::<C>),
+                }));
+                let name = CString::new(C::NAME).expect("Class names cannot contain NUL");
+                Box::leak(Box::new(JSClass {
+                    name: name.into_raw(),
+                    flags: JSCLASS_FOREGROUND_FINALIZE
+                        | ((1 & JSCLASS_RESERVED_SLOTS_MASK) << JSCLASS_RESERVED_SLOTS_SHIFT),
+                    cOps: ops,
+                    spec: ptr::null(),
+                    ext: ptr::null(),
+                    oOps: ptr::null(),
+                }))
+            })
+    })
+}
+
+/// Returns the private data of `obj`, or null if it has none, like the
+/// prototype of the class.
+unsafe fn private_ptr<C: NativeClass>(obj: *mut JSObject) -> *mut C::Private {
+    let mut slot = UndefinedValue();
+    JS_GetReservedSlot(obj, PRIVATE_SLOT, &mut slot);
+    if slot.is_undefined() {
+        return ptr::null_mut();
+    }
+    slot.to_private() as *mut C::Private
+}
+
+unsafe fn set_private<C: NativeClass>(obj: *mut JSObject, private: C::Private) {
+    let private = Box::into_raw(Box::new(private));
+    JS_SetReservedSlot(obj, PRIVATE_SLOT, &PrivateValue(private as *const c_void));
+}
+
+/// Returns the private data of `obj` if it is an instance of `C`.
+///
+/// Cross-compartment wrappers are not unwrapped, so they are never
+/// considered instances. The reference must not outlive `obj`.
+pub unsafe fn unwrap_private<'a, C: NativeClass>(obj: *mut JSObject) -> Option<&'a C::Private> {
+    if get_object_class(obj) != class_of::<C>() as *const JSClass {
+        return None;
+    }
+    private_ptr::<C>(obj).as_ref()
+}
+
+/// Creates an instance of `C` holding `private`, with the prototype
+/// `proto`, which is usually the one returned by `ClassBuilder::init`.
+pub unsafe fn new_instance<C: NativeClass>(
+    cx: *mut JSContext,
+    proto: HandleObject,
+    private: C::Private,
+) -> Result<*mut JSObject, ()> {
+    let obj = JS_NewObjectWithGivenProto(cx, class_of::<C>(), proto.into());
+    if obj.is_null() {
+        return Err(());
+    }
+    set_private::<C>(obj, private);
+    Ok(obj)
+}
+
+unsafe extern "C" fn finalize<C: NativeClass>(_fop: *mut JSFreeOp, obj: *mut JSObject) {
+    let private = private_ptr::<C>(obj);
+    if !private.is_null() {
+        wrap_panic(&mut || drop(Box::from_raw(private)));
+    }
+}
+
+unsafe extern "C" fn trace<C: NativeClass>(trc: *mut JSTracer, obj: *mut JSObject) {
+    let private = private_ptr::<C>(obj);
+    if !private.is_null() {
+        wrap_panic(&mut || (*private).trace(trc));
+    }
+}
+
+unsafe extern "C" fn construct<C, F, Args>(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool
+where
+    C: NativeClass,
+    F: NativeConstructor<C::Private, Args>,
+{
+    let args = CallArgs::from_vp(vp, argc);
+    call_native(cx, &mut || {
+        if !args.constructing_() {
+            return Err(JsError::TypeError(format!(
+                "{} constructor requires 'new'",
+                C::NAME
+            )));
+        }
+        let private = zero_sized::<F>().construct(cx, &args)?;
+        let obj = JS_NewObjectForConstructor(cx, class_of::<C>(), &args);
+        if obj.is_null() {
+            return Err(JsError::Pending);
+        }
+        set_private::<C>(obj, private);
+        MutableHandleValue::from_raw(args.rval()).set(ObjectValue(obj));
+        Ok(())
+    })
+}
+
+unsafe extern "C" fn illegal_constructor<C: NativeClass>(
+    cx: *mut JSContext,
+    _argc: u32,
+    _vp: *mut Value,
+) -> bool {
+    call_native(cx, &mut || {
+        Err(JsError::TypeError(format!(
+            "{} has no constructor",
+            C::NAME
+        )))
+    })
+}
+
+unsafe extern "C" fn call_method<C, F, Args>(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool
+where
+    C: NativeClass,
+    F: NativeClosure<C::Private, Args>,
+{
+    let args = CallArgs::from_vp(vp, argc);
+    call_native(cx, &mut || {
+        let this = Handle::from_raw(args.thisv());
+        let private = if this.is_object() {
+            unwrap_private::<C>(this.to_object())
+        } else {
+            None
+        };
+        match private {
+            Some(private) => zero_sized::<F>().call(private, cx, &args),
+            None => Err(JsError::TypeError(format!("'this' is not a {}", C::NAME))),
+        }
+    })
+}
+
+/// Registers a `NativeClass` on a global: its constructor, and the methods
+/// and accessor properties of its prototype.
+pub struct ClassBuilder<C: NativeClass> {
+    constructor: Option<(JSNative, u32)>,
+    methods: Vec<(String, JSNative, u32)>,
+    properties: Vec<(String, JSNative, JSNative)>,
+    marker: PhantomData<C>,
+}
+
+impl<C: NativeClass> Default for ClassBuilder<C> {
+    fn default() -> ClassBuilder<C> {
+        ClassBuilder::new()
+    }
+}
+
+impl<C: NativeClass> ClassBuilder<C> {
+    /// Creates a builder for a class without constructor, methods or
+    /// properties. Calling the constructor of such a class throws.
+    pub fn new() -> ClassBuilder<C> {
+        ClassBuilder {
+            constructor: None,
+            methods: Vec::new(),
+            properties: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the function called by `new C(...)` to create the private data
+    /// of an instance.
+    pub fn constructor<F, Args>(mut self, f: F) -> ClassBuilder<C>
+    where
+        F: NativeConstructor<C::Private, Args>,
+    {
+        assert_zero_sized::<F>();
+        mem::forget(f);
+        self.constructor = Some((Some(construct::<C, F, Args>), F::ARITY));
+        self
+    }
+
+    /// Adds a method to the prototype. `f` receives the private data of the
+    /// `this` object before the JS arguments.
+    pub fn method<F, Args>(mut self, name: &str, f: F) -> ClassBuilder<C>
+    where
+        F: NativeClosure<C::Private, Args>,
+    {
+        assert_zero_sized::<F>();
+        mem::forget(f);
+        self.methods
+            .push((name.to_owned(), Some(call_method::<C, F, Args>), F::ARITY));
+        self
+    }
+
+    /// Adds a read-only accessor property to the prototype.
+    pub fn property<G>(mut self, name: &str, getter: G) -> ClassBuilder<C>
+    where
+        G: NativeClosure<C::Private, ()>,
+    {
+        assert_zero_sized::<G>();
+        mem::forget(getter);
+        self.properties
+            .push((name.to_owned(), Some(call_method::<C, G, ()>), None));
+        self
+    }
+
+    /// Adds an accessor property with a setter to the prototype.
+    pub fn accessor<G, S, V>(mut self, name: &str, getter: G, setter: S) -> ClassBuilder<C>
+    where
+        G: NativeClosure<C::Private, ()>,
+        S: NativeClosure<C::Private, (V,)>,
+    {
+        assert_zero_sized::<G>();
+        assert_zero_sized::<S>();
+        mem::forget(getter);
+        mem::forget(setter);
+        self.properties.push((
+            name.to_owned(),
+            Some(call_method::<C, G, ()>),
+            Some(call_method::<C, S, (V,)>),
+        ));
+        self
+    }
+
+    /// Defines the class on `global`, returning its prototype.
+    pub unsafe fn init(
+        self,
+        cx: *mut JSContext,
+        global: HandleObject,
+    ) -> Result<*mut JSObject, ()> {
+        let (constructor, nargs) = self
+            .constructor
+            .unwrap_or((Some(illegal_constructor::<C>), 0));
+        rooted!(in(cx) let proto = JS_InitClass(
+            cx,
+            global.into(),
+            HandleObject::null().into(),
+            class_of::<C>(),
+            constructor,
+            nargs,
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+        ));
+        if proto.is_null() {
+            return Err(());
+        }
+
+        for (name, native, nargs) in self.methods {
+            let name: Vec<u16> = name.encode_utf16().collect();
+            let function = JS_DefineUCFunction(
+                cx,
+                proto.handle().into(),
+                name.as_ptr(),
+                name.len(),
+                native,
+                nargs,
+                0,
+            );
+            if function.is_null() {
+                return Err(());
+            }
+        }
+
+        for (name, getter, setter) in self.properties {
+            rooted!(in(cx) let mut id: jsid);
+            string_id(cx, &name, id.handle_mut())?;
+            if !JS_DefinePropertyById3(
+                cx,
+                proto.handle().into(),
+                id.handle().into(),
+                getter,
+                setter,
+                0,
+            ) {
+                return Err(());
+            }
+        }
+        Ok(proto.get())
+    }
+}
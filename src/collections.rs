@@ -0,0 +1,635 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Rooted wrappers for JS `Map` and `Set` objects.
+//!
+//! Keys and values are converted with the traits of the `conversions`
+//! module. Methods return `Err(())` when a JSAPI call fails, with an
+//! exception pending on the context; values that fail to convert are
+//! reported as a `TypeError`.
+
+use conversions::{ConversionResult, FromJSValConvertible, ToJSValConvertible};
+use error::throw_type_error;
+use jsapi::{HandleValueArray, Heap, IsMapObject, IsSetObject, JSContext, JSObject};
+use jsapi::{JS_CallFunctionName, JS_GetElement, JS_GetProperty, NewMapObject, NewSetObject};
+use jsapi::{MapClear, MapDelete, MapEntries, MapGet, MapHas, MapSet, MapSize};
+use jsapi::{SetAdd, SetClear, SetDelete, SetHas, SetSize, SetValues};
+use jsval::{ObjectValue, UndefinedValue};
+use rust::{HandleObject, HandleValue, MutableHandleValue, RootedTraceableBox, ToBoolean};
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+
+unsafe fn from_jsval<T: FromJSValConvertible>(
+    cx: *mut JSContext,
+    value: HandleValue,
+    option: T::Config,
+) -> Result<T, ()> {
+    match T::from_jsval(cx, value, option)? {
+        ConversionResult::Success(v) => Ok(v),
+        ConversionResult::Failure(e) => {
+            throw_type_error(cx, &e);
+            Err(())
+        }
+    }
+}
+
+fn root(object: *mut JSObject) -> RootedTraceableBox<Heap<*mut JSObject>> {
+    RootedTraceableBox::from_box(Heap::boxed(object))
+}
+
+/// A rooted handle to a JS `Map`.
+pub struct JSMap {
+    object: RootedTraceableBox<Heap<*mut JSObject>>,
+}
+
+impl JSMap {
+    /// Creates an empty map in the current realm.
+    pub unsafe fn new(cx: *mut JSContext) -> Result<JSMap, ()> {
+        let object = NewMapObject(cx);
+        if object.is_null() {
+            return Err(());
+        }
+        Ok(JSMap {
+            object: root(object),
+        })
+    }
+
+    /// Creates a map in the current realm holding `entries`.
+    pub unsafe fn from_entries<'a, K, V, I>(cx: *mut JSContext, entries: I) -> Result<JSMap, ()>
+    where
+        K: ToJSValConvertible + 'a,
+        V: ToJSValConvertible + 'a,
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        let map = JSMap::new(cx)?;
+        for (key, value) in entries {
+            map.set(cx, key, value)?;
+        }
+        Ok(map)
+    }
+
+    /// Wraps an existing object, returning `None` if it is not a map.
+    pub unsafe fn from_object(
+        cx: *mut JSContext,
+        object: HandleObject,
+    ) -> Result<Option<JSMap>, ()> {
+        let mut is_map = false;
+        if !IsMapObject(cx, object.into(), &mut is_map) {
+            return Err(());
+        }
+        if !is_map {
+            return Ok(None);
+        }
+        Ok(Some(JSMap {
+            object: root(object.get()),
+        }))
+    }
+
+    /// Returns a handle to the map object.
+    pub fn handle(&self) -> HandleObject {
+        self.object.handle()
+    }
+
+    /// Returns the number of entries.
+    pub unsafe fn len(&self, cx: *mut JSContext) -> usize {
+        MapSize(cx, self.handle().into()) as usize
+    }
+
+    /// Returns whether the map has no entries.
+    pub unsafe fn is_empty(&self, cx: *mut JSContext) -> bool {
+        self.len(cx) == 0
+    }
+
+    /// Returns the value for `key`, or `None` if there is no entry for it.
+    pub unsafe fn get<K, V>(
+        &self,
+        cx: *mut JSContext,
+        key: &K,
+        option: V::Config,
+    ) -> Result<Option<V>, ()>
+    where
+        K: ToJSValConvertible + ?Sized,
+        V: FromJSValConvertible,
+    {
+        rooted!(in(cx) let mut js_key = UndefinedValue());
+        key.to_jsval(cx, js_key.handle_mut());
+        let mut has = false;
+        if !MapHas(cx, self.handle().into(), js_key.handle().into(), &mut has) {
+            return Err(());
+        }
+        if !has {
+            return Ok(None);
+        }
+        rooted!(in(cx) let mut value = UndefinedValue());
+        if !MapGet(
+            cx,
+            self.handle().into(),
+            js_key.handle().into(),
+            value.handle_mut().into(),
+        ) {
+            return Err(());
+        }
+        from_jsval(cx, value.handle(), option).map(Some)
+    }
+
+    /// Sets the value for `key`.
+    pub unsafe fn set<K, V>(&self, cx: *mut JSContext, key: &K, value: &V) -> Result<(), ()>
+    where
+        K: ToJSValConvertible + ?Sized,
+        V: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_key = UndefinedValue());
+        key.to_jsval(cx, js_key.handle_mut());
+        rooted!(in(cx) let mut js_value = UndefinedValue());
+        value.to_jsval(cx, js_value.handle_mut());
+        if MapSet(
+            cx,
+            self.handle().into(),
+            js_key.handle().into(),
+            js_value.handle().into(),
+        ) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Returns whether there is an entry for `key`.
+    pub unsafe fn has<K>(&self, cx: *mut JSContext, key: &K) -> Result<bool, ()>
+    where
+        K: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_key = UndefinedValue());
+        key.to_jsval(cx, js_key.handle_mut());
+        let mut has = false;
+        if MapHas(cx, self.handle().into(), js_key.handle().into(), &mut has) {
+            Ok(has)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Removes the entry for `key`, returning whether there was one.
+    pub unsafe fn delete<K>(&self, cx: *mut JSContext, key: &K) -> Result<bool, ()>
+    where
+        K: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_key = UndefinedValue());
+        key.to_jsval(cx, js_key.handle_mut());
+        let mut deleted = false;
+        if MapDelete(
+            cx,
+            self.handle().into(),
+            js_key.handle().into(),
+            &mut deleted,
+        ) {
+            Ok(deleted)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Removes all entries.
+    pub unsafe fn clear(&self, cx: *mut JSContext) -> Result<(), ()> {
+        if MapClear(cx, self.handle().into()) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Returns an iterator over the entries, in insertion order. Entries
+    /// added while iterating are visited too, like in JS.
+    pub unsafe fn entries<K, V>(
+        &self,
+        cx: *mut JSContext,
+        key_option: K::Config,
+        value_option: V::Config,
+    ) -> Result<Entries<K, V>, ()>
+    where
+        K: FromJSValConvertible,
+        V: FromJSValConvertible,
+        K::Config: Clone,
+        V::Config: Clone,
+    {
+        rooted!(in(cx) let mut iterator = UndefinedValue());
+        if !MapEntries(cx, self.handle().into(), iterator.handle_mut().into()) {
+            return Err(());
+        }
+        Ok(Entries {
+            iterator: JSIterator::new(cx, iterator.to_object()),
+            key_option,
+            value_option,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// A rooted handle to a JS `Set`.
+pub struct JSSet {
+    object: RootedTraceableBox<Heap<*mut JSObject>>,
+}
+
+impl JSSet {
+    /// Creates an empty set in the current realm.
+    pub unsafe fn new(cx: *mut JSContext) -> Result<JSSet, ()> {
+        let object = NewSetObject(cx);
+        if object.is_null() {
+            return Err(());
+        }
+        Ok(JSSet {
+            object: root(object),
+        })
+    }
+
+    /// Creates a set in the current realm holding `values`.
+    pub unsafe fn from_values<'a, T, I>(cx: *mut JSContext, values: I) -> Result<JSSet, ()>
+    where
+        T: ToJSValConvertible + 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let set = JSSet::new(cx)?;
+        for value in values {
+            set.add(cx, value)?;
+        }
+        Ok(set)
+    }
+
+    /// Wraps an existing object, returning `None` if it is not a set.
+    pub unsafe fn from_object(
+        cx: *mut JSContext,
+        object: HandleObject,
+    ) -> Result<Option<JSSet>, ()> {
+        let mut is_set = false;
+        if !IsSetObject(cx, object.into(), &mut is_set) {
+            return Err(());
+        }
+        if !is_set {
+            return Ok(None);
+        }
+        Ok(Some(JSSet {
+            object: root(object.get()),
+        }))
+    }
+
+    /// Returns a handle to the set object.
+    pub fn handle(&self) -> HandleObject {
+        self.object.handle()
+    }
+
+    /// Returns the number of values.
+    pub unsafe fn len(&self, cx: *mut JSContext) -> usize {
+        SetSize(cx, self.handle().into()) as usize
+    }
+
+    /// Returns whether the set has no values.
+    pub unsafe fn is_empty(&self, cx: *mut JSContext) -> bool {
+        self.len(cx) == 0
+    }
+
+    /// Adds `value` to the set.
+    pub unsafe fn add<T>(&self, cx: *mut JSContext, value: &T) -> Result<(), ()>
+    where
+        T: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_value = UndefinedValue());
+        value.to_jsval(cx, js_value.handle_mut());
+        if SetAdd(cx, self.handle().into(), js_value.handle().into()) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Returns whether `value` is in the set.
+    pub unsafe fn has<T>(&self, cx: *mut JSContext, value: &T) -> Result<bool, ()>
+    where
+        T: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_value = UndefinedValue());
+        value.to_jsval(cx, js_value.handle_mut());
+        let mut has = false;
+        if SetHas(cx, self.handle().into(), js_value.handle().into(), &mut has) {
+            Ok(has)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Removes `value`, returning whether it was in the set.
+    pub unsafe fn delete<T>(&self, cx: *mut JSContext, value: &T) -> Result<bool, ()>
+    where
+        T: ToJSValConvertible + ?Sized,
+    {
+        rooted!(in(cx) let mut js_value = UndefinedValue());
+        value.to_jsval(cx, js_value.handle_mut());
+        let mut deleted = false;
+        if SetDelete(
+            cx,
+            self.handle().into(),
+            js_value.handle().into(),
+            &mut deleted,
+        ) {
+            Ok(deleted)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Removes all values.
+    pub unsafe fn clear(&self, cx: *mut JSContext) -> Result<(), ()> {
+        if SetClear(cx, self.handle().into()) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Returns an iterator over the values, in insertion order.
+    pub unsafe fn values<T>(&self, cx: *mut JSContext, option: T::Config) -> Result<Values<T>, ()>
+    where
+        T: FromJSValConvertible,
+        T::Config: Clone,
+    {
+        rooted!(in(cx) let mut iterator = UndefinedValue());
+        if !SetValues(cx, self.handle().into(), iterator.handle_mut().into()) {
+            return Err(());
+        }
+        Ok(Values {
+            iterator: JSIterator::new(cx, iterator.to_object()),
+            option,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// A JS iterator object, stepped by calling its `next` method.
+struct JSIterator {
+    cx: *mut JSContext,
+    object: RootedTraceableBox<Heap<*mut JSObject>>,
+    done: bool,
+}
+
+impl JSIterator {
+    unsafe fn new(cx: *mut JSContext, object: *mut JSObject) -> JSIterator {
+        JSIterator {
+            cx,
+            object: root(object),
+            done: false,
+        }
+    }
+
+    /// Stores the next value in `rval`, returning `Ok(false)` once the
+    /// iterator is exhausted.
+    unsafe fn next(&mut self, rval: MutableHandleValue) -> Result<bool, ()> {
+        if self.done {
+            return Ok(false);
+        }
+        let cx = self.cx;
+        rooted!(in(cx) let mut result = UndefinedValue());
+        let args = HandleValueArray::new();
+        if !JS_CallFunctionName(
+            cx,
+            self.object.handle().into(),
+            b"next\0".as_ptr() as *const c_char,
+            &args,
+            result.handle_mut().into(),
+        ) {
+            self.done = true;
+            return Err(());
+        }
+        if !result.is_object() {
+            self.done = true;
+            throw_type_error(cx, "Iterator result is not an object");
+            return Err(());
+        }
+        rooted!(in(cx) let result = result.to_object());
+        rooted!(in(cx) let mut done = UndefinedValue());
+        if !JS_GetProperty(
+            cx,
+            result.handle().into(),
+            b"done\0".as_ptr() as *const c_char,
+            done.handle_mut().into(),
+        ) {
+            self.done = true;
+            return Err(());
+        }
+        if ToBoolean(done.handle()) {
+            self.done = true;
+            return Ok(false);
+        }
+        if !JS_GetProperty(
+            cx,
+            result.handle().into(),
+            b"value\0".as_ptr() as *const c_char,
+            rval.into(),
+        ) {
+            self.done = true;
+            return Err(());
+        }
+        Ok(true)
+    }
+}
+
+/// An iterator over the entries of a `JSMap`.
+pub struct Entries<K: FromJSValConvertible, V: FromJSValConvertible> {
+    iterator: JSIterator,
+    key_option: K::Config,
+    value_option: V::Config,
+    marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> Iterator for Entries<K, V>
+where
+    K: FromJSValConvertible,
+    V: FromJSValConvertible,
+    K::Config: Clone,
+    V::Config: Clone,
+{
+    type Item = Result<(K, V), ()>;
+
+    fn next(&mut self) -> Option<Result<(K, V), ()>> {
+        unsafe {
+            let cx = self.iterator.cx;
+            rooted!(in(cx) let mut entry = UndefinedValue());
+            match self.iterator.next(entry.handle_mut()) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(()) => return Some(Err(())),
+            }
+            // Entries are `[key, value]` arrays.
+            if !entry.is_object() {
+                throw_type_error(cx, "Map entry is not an object");
+                return Some(Err(()));
+            }
+            rooted!(in(cx) let entry = entry.to_object());
+            rooted!(in(cx) let mut key = UndefinedValue());
+            rooted!(in(cx) let mut value = UndefinedValue());
+            if !JS_GetElement(cx, entry.handle().into(), 0, key.handle_mut().into())
+                || !JS_GetElement(cx, entry.handle().into(), 1, value.handle_mut().into())
+            {
+                return Some(Err(()));
+            }
+            let key = match from_jsval(cx, key.handle(), self.key_option.clone()) {
+                Ok(key) => key,
+                Err(()) => return Some(Err(())),
+            };
+            Some(
+                from_jsval(cx, value.handle(), self.value_option.clone()).map(|value| (key, value)),
+            )
+        }
+    }
+}
+
+/// An iterator over the values of a `JSSet`.
+pub struct Values<T: FromJSValConvertible> {
+    iterator: JSIterator,
+    option: T::Config,
+    marker: PhantomData<T>,
+}
+
+impl<T> Iterator for Values<T>
+where
+    T: FromJSValConvertible,
+    T::Config: Clone,
+{
+    type Item = Result<T, ()>;
+
+    fn next(&mut self) -> Option<Result<T, ()>> {
+        unsafe {
+            let cx = self.iterator.cx;
+            rooted!(in(cx) let mut value = UndefinedValue());
+            match self.iterator.next(value.handle_mut()) {
+                Ok(true) => Some(from_jsval(cx, value.handle(), self.option.clone())),
+                Ok(false) => None,
+                Err(()) => Some(Err(())),
+            }
+        }
+    }
+}
+
+impl ToJSValConvertible for JSMap {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        self.object.to_jsval(cx, rval)
+    }
+}
+
+impl FromJSValConvertible for JSMap {
+    type Config = ();
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        _option: (),
+    ) -> Result<ConversionResult<JSMap>, ()> {
+        if !value.is_object() {
+            return Ok(ConversionResult::Failure("Value is not an object".into()));
+        }
+        rooted!(in(cx) let object = value.to_object());
+        Ok(match JSMap::from_object(cx, object.handle())? {
+            Some(map) => ConversionResult::Success(map),
+            None => ConversionResult::Failure("Value is not a Map".into()),
+        })
+    }
+}
+
+impl ToJSValConvertible for JSSet {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        self.object.to_jsval(cx, rval)
+    }
+}
+
+impl FromJSValConvertible for JSSet {
+    type Config = ();
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        _option: (),
+    ) -> Result<ConversionResult<JSSet>, ()> {
+        if !value.is_object() {
+            return Ok(ConversionResult::Failure("Value is not an object".into()));
+        }
+        rooted!(in(cx) let object = value.to_object());
+        Ok(match JSSet::from_object(cx, object.handle())? {
+            Some(set) => ConversionResult::Success(set),
+            None => ConversionResult::Failure("Value is not a Set".into()),
+        })
+    }
+}
+
+/// A Rust map that is converted to and from a JS `Map`, rather than a
+/// record like a bare `HashMap<String, T>`.
+///
+/// Keys and values are converted with their own `Config`, passed as a
+/// `(key_config, value_config)` pair.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AsMap<M>(pub M);
+
+impl<K, V, S> ToJSValConvertible for AsMap<HashMap<K, V, S>>
+where
+    K: ToJSValConvertible,
+    V: ToJSValConvertible,
+{
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        let map = JSMap::from_entries(cx, &self.0).expect("Creating a Map failed");
+        rval.set(ObjectValue(map.handle().get()));
+    }
+}
+
+impl<K, V, S> FromJSValConvertible for AsMap<HashMap<K, V, S>>
+where
+    K: FromJSValConvertible + Eq + Hash,
+    V: FromJSValConvertible,
+    K::Config: Clone,
+    V::Config: Clone,
+    S: BuildHasher + Default,
+{
+    type Config = (K::Config, V::Config);
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        (key_option, value_option): (K::Config, V::Config),
+    ) -> Result<ConversionResult<AsMap<HashMap<K, V, S>>>, ()> {
+        let map = match JSMap::from_jsval(cx, value, ())? {
+            ConversionResult::Success(map) => map,
+            ConversionResult::Failure(e) => return Ok(ConversionResult::Failure(e)),
+        };
+        let entries = map
+            .entries(cx, key_option, value_option)?
+            .collect::<Result<_, ()>>()?;
+        Ok(ConversionResult::Success(AsMap(entries)))
+    }
+}
+
+impl<T: ToJSValConvertible, S> ToJSValConvertible for HashSet<T, S> {
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        let set = JSSet::from_values(cx, self).expect("Creating a Set failed");
+        rval.set(ObjectValue(set.handle().get()));
+    }
+}
+
+impl<T, S> FromJSValConvertible for HashSet<T, S>
+where
+    T: FromJSValConvertible + Eq + Hash,
+    T::Config: Clone,
+    S: BuildHasher + Default,
+{
+    type Config = T::Config;
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: T::Config,
+    ) -> Result<ConversionResult<HashSet<T, S>>, ()> {
+        let set = match JSSet::from_jsval(cx, value, ())? {
+            ConversionResult::Success(set) => set,
+            ConversionResult::Failure(e) => return Ok(ConversionResult::Failure(e)),
+        };
+        let values = set.values(cx, option)?.collect::<Result<_, ()>>()?;
+        Ok(ConversionResult::Success(values))
+    }
+}
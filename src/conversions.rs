@@ -16,40 +16,66 @@
 //! | unsigned long           | `u32`                            |
 //! | long long               | `i64`                            |
 //! | unsigned long long      | `u64`                            |
+//! | bigint                  | `AsBigInt<T>`, `i128`, `u128`    |
 //! | unrestricted float      | `f32`                            |
 //! | float                   | `Finite<f32>`                    |
 //! | unrestricted double     | `f64`                            |
 //! | double                  | `Finite<f64>`                    |
 //! | USVString               | `String`                         |
 //! | object                  | `*mut JSObject`                  |
+//! | Date                    | `SystemTime`, `DateTime<Utc>`    |
 //! | symbol                  | `*mut Symbol`                    |
 //! | nullable types          | `Option<T>`                      |
 //! | sequences               | `Vec<T>`                         |
+//! | records                 | `HashMap<String, T>`, `BTreeMap` |
+//! | fixed-length sequences  | tuples, `[T; N]`                 |
+//! | dictionaries            | `#[derive]`d structs             |
+//! | enumerations            | `#[derive]`d fieldless enums     |
+//!
+//! `i64` and `u64` convert from Numbers, or from BigInts with
+//! `ConversionBehavior::BigInt`, and to Numbers, or to BigInts with
+//! `AsBigInt`.
 
 #![deny(missing_docs)]
 
-use error::throw_type_error;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use error::{throw_type_error, JsError};
 use glue::RUST_JS_NumberValue;
+use glue::{BigIntFromDecimalString, NewDateObjectFromMsec, UnwrapObjectStatic};
 use jsapi::AssertSameCompartment;
 use jsapi::JS;
+use jsapi::{BigInt, BigIntFromInt64, BigIntFromUint64, BigIntIsInt64, BigIntIsUint64};
+use jsapi::{BigIntToString, NumberToBigInt, ToBigInt};
+use jsapi::{DateGetMsecSinceEpoch, DateIsValid, JSAutoRealm, ObjectIsDate};
 use jsapi::{ForOfIterator, ForOfIterator_NonIterableBehavior};
+use jsapi::{GetArrayLength, IsArrayObject1, JS_GetElement};
+use jsapi::{GetPropertyKeys, JS_GetPropertyById, JS_IdToValue, JSITER_OWNONLY};
 use jsapi::{Heap, JS_DefineElement, JS_GetLatin1StringCharsAndLength};
 use jsapi::{JSContext, JSObject, JSString, RootedObject, RootedValue};
+use jsapi::{JS_DefineUCProperty2, JS_NewPlainObject};
 use jsapi::{JS_DeprecatedStringHasLatin1Chars, JS_NewUCStringCopyN, JSPROP_ENUMERATE};
 use jsapi::{JS_GetTwoByteStringCharsAndLength, NewArrayObject1};
-use jsval::{BooleanValue, Int32Value, NullValue, UInt32Value, UndefinedValue};
+use jsval::{BigIntValue, BooleanValue, Int32Value, NullValue, UInt32Value, UndefinedValue};
 use jsval::{JSVal, ObjectOrNullValue, ObjectValue, StringValue, SymbolValue};
 use libc;
 use num_traits::{Bounded, Zero};
 use rust::maybe_wrap_value;
 use rust::{maybe_wrap_object_or_null_value, maybe_wrap_object_value, ToString};
-use rust::{HandleValue, MutableHandleValue};
+use rust::{HandleBigInt, HandleObject, HandleValue, IdVector, MutableHandleValue};
 use rust::{ToBoolean, ToInt32, ToInt64, ToNumber, ToUint16, ToUint32, ToUint64};
 use std::borrow::Cow;
-use std::mem;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::hash::BuildHasher;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
 use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{ptr, slice};
 
+#[cfg(feature = "derive")]
+pub use mozjs_derive::{FromJSValConvertible, ToJSValConvertible};
+
 trait As<O>: Copy {
     fn cast(self) -> O;
 }
@@ -114,12 +140,23 @@ impl<T> ConversionResult<T> {
             _ => None,
         }
     }
+
+    /// Returns the value, or a `JsError::TypeError` with the failure message.
+    pub fn into_result(self) -> Result<T, JsError> {
+        match self {
+            ConversionResult::Success(v) => Ok(v),
+            ConversionResult::Failure(message) => Err(JsError::TypeError(message.into_owned())),
+        }
+    }
 }
 
 /// A trait to convert `JSVal`s to Rust types.
 pub trait FromJSValConvertible: Sized {
     /// Optional configurable behaviour switch; use () for no configuration.
     type Config;
+    /// Whether the value may be omitted when it is a trailing argument of a
+    /// native function, in which case it is converted from `undefined`.
+    const OPTIONAL: bool = false;
     /// Convert `val` to type `Self`.
     /// Optional configuration of type `T` can be passed as the `option`
     /// argument.
@@ -141,6 +178,15 @@ pub enum ConversionBehavior {
     EnforceRange,
     /// Clamp into the integer's range.
     Clamp,
+    /// Convert 64-bit integers from BigInts rather than Numbers, wrapping
+    /// into the integer's range. Other integers treat this like `Default`.
+    BigInt,
+}
+
+impl Default for ConversionBehavior {
+    fn default() -> ConversionBehavior {
+        ConversionBehavior::Default
+    }
 }
 
 /// Try to cast the number to a smaller type, but
@@ -239,7 +285,9 @@ where
     f64: As<T>,
 {
     match option {
-        ConversionBehavior::Default => Ok(ConversionResult::Success(convert_fn(cx, value)?.cast())),
+        ConversionBehavior::Default | ConversionBehavior::BigInt => {
+            Ok(ConversionResult::Success(convert_fn(cx, value)?.cast()))
+        }
         ConversionBehavior::EnforceRange => enforce_range(cx, ToNumber(cx, value)?),
         ConversionBehavior::Clamp => Ok(ConversionResult::Success(clamp_to(ToNumber(cx, value)?))),
     }
@@ -401,6 +449,9 @@ impl FromJSValConvertible for i64 {
         val: HandleValue,
         option: ConversionBehavior,
     ) -> Result<ConversionResult<i64>, ()> {
+        if option == ConversionBehavior::BigInt {
+            return bigint_from_jsval(cx, val, option);
+        }
         convert_int_from_jsval(cx, val, option, ToInt64)
     }
 }
@@ -421,10 +472,268 @@ impl FromJSValConvertible for u64 {
         val: HandleValue,
         option: ConversionBehavior,
     ) -> Result<ConversionResult<u64>, ()> {
+        if option == ConversionBehavior::BigInt {
+            return bigint_from_jsval(cx, val, option);
+        }
         convert_int_from_jsval(cx, val, option, ToUint64)
     }
 }
 
+/// A 64-bit or 128-bit integer that is converted to and from a JS BigInt,
+/// rather than a Number.
+///
+/// When converting from JS, Numbers must be integral, and other values are
+/// converted with the ToBigInt operation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsBigInt<T>(pub T);
+
+/// Integer types that can be converted from BigInts.
+trait BigIntRange: Bounded {
+    /// The magnitude of the smallest value.
+    const MIN_MAGNITUDE: u128;
+    /// The magnitude of the largest value.
+    const MAX_MAGNITUDE: u128;
+    /// Truncates a value modulo 2^128 to this type.
+    fn wrap(value: u128) -> Self;
+}
+
+macro_rules! bigint_range {
+    ($t:ty, $min_magnitude:expr) => {
+        impl BigIntRange for $t {
+            const MIN_MAGNITUDE: u128 = $min_magnitude;
+            const MAX_MAGNITUDE: u128 = <$t>::MAX as u128;
+            fn wrap(value: u128) -> $t {
+                value as $t
+            }
+        }
+    };
+}
+
+bigint_range!(i64, 1 << 63);
+bigint_range!(u64, 0);
+bigint_range!(i128, 1 << 127);
+bigint_range!(u128, 0);
+
+unsafe fn i128_to_bigint(cx: *mut JSContext, value: i128) -> *mut BigInt {
+    if let Ok(value) = i64::try_from(value) {
+        return BigIntFromInt64(cx, value);
+    }
+    if let Ok(value) = u64::try_from(value) {
+        return BigIntFromUint64(cx, value);
+    }
+    let digits = value.to_string();
+    BigIntFromDecimalString(cx, digits.as_ptr() as *const libc::c_char, digits.len())
+}
+
+/// Stores `bigint` in `rval`, panicking if creating it failed.
+unsafe fn bigint_to_jsval(bigint: *mut BigInt, mut rval: MutableHandleValue) {
+    if bigint.is_null() {
+        panic!("Creating a BigInt failed");
+    }
+    rval.set(BigIntValue(&*bigint));
+}
+
+// https://heycam.github.io/webidl/#es-bigint
+unsafe fn bigint_from_jsval<T: BigIntRange>(
+    cx: *mut JSContext,
+    value: HandleValue,
+    option: ConversionBehavior,
+) -> Result<ConversionResult<T>, ()> {
+    let bigint = if value.is_bigint() {
+        value.to_bigint()
+    } else if value.is_number() {
+        NumberToBigInt(cx, value.to_number())
+    } else {
+        ToBigInt(cx, value.into())
+    };
+    rooted!(in(cx) let bigint = bigint);
+    if bigint.handle().is_null() {
+        return Err(());
+    }
+
+    let (negative, magnitude, wrapped) = bigint_parts(cx, bigint.handle())?;
+    let in_range = match magnitude {
+        Some(magnitude) if negative => magnitude <= T::MIN_MAGNITUDE,
+        Some(magnitude) => magnitude <= T::MAX_MAGNITUDE,
+        None => false,
+    };
+    match option {
+        _ if in_range => Ok(ConversionResult::Success(T::wrap(wrapped))),
+        ConversionBehavior::Default | ConversionBehavior::BigInt => {
+            Ok(ConversionResult::Success(T::wrap(wrapped)))
+        }
+        ConversionBehavior::EnforceRange => {
+            throw_type_error(cx, "value out of range in an EnforceRange argument");
+            Err(())
+        }
+        ConversionBehavior::Clamp if negative => Ok(ConversionResult::Success(T::min_value())),
+        ConversionBehavior::Clamp => Ok(ConversionResult::Success(T::max_value())),
+    }
+}
+
+/// Returns the sign of `bigint`, its magnitude if it fits in 128 bits, and
+/// its value modulo 2^128.
+unsafe fn bigint_parts(
+    cx: *mut JSContext,
+    bigint: HandleBigInt,
+) -> Result<(bool, Option<u128>, u128), ()> {
+    let mut signed = 0;
+    if BigIntIsInt64(bigint.get(), &mut signed) {
+        return Ok((
+            signed < 0,
+            Some(signed.unsigned_abs() as u128),
+            signed as i128 as u128,
+        ));
+    }
+    let mut unsigned = 0;
+    if BigIntIsUint64(bigint.get(), &mut unsigned) {
+        return Ok((false, Some(unsigned as u128), unsigned as u128));
+    }
+
+    // Only 128-bit integers hold larger values, which are read from their
+    // decimal digits.
+    rooted!(in(cx) let string = BigIntToString(cx, bigint.into(), 10));
+    if string.handle().is_null() {
+        return Err(());
+    }
+    let string = jsstr_to_string(cx, string.get());
+    let (negative, digits) = match string.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, &*string),
+    };
+    let mut wrapped = 0u128;
+    let mut magnitude = Some(0u128);
+    for digit in digits.bytes() {
+        let digit = (digit - b'0') as u128;
+        wrapped = wrapped.wrapping_mul(10).wrapping_add(digit);
+        magnitude = magnitude
+            .and_then(|m| m.checked_mul(10))
+            .and_then(|m| m.checked_add(digit));
+    }
+    if negative {
+        wrapped = wrapped.wrapping_neg();
+    }
+    Ok((negative, magnitude, wrapped))
+}
+
+impl ToJSValConvertible for *mut BigInt {
+    #[inline]
+    unsafe fn to_jsval(&self, _: *mut JSContext, mut rval: MutableHandleValue) {
+        rval.set(BigIntValue(&**self));
+    }
+}
+
+impl FromJSValConvertible for *mut BigInt {
+    type Config = ();
+    #[inline]
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        _option: (),
+    ) -> Result<ConversionResult<*mut BigInt>, ()> {
+        if !value.is_bigint() {
+            throw_type_error(cx, "value is not a BigInt");
+            return Err(());
+        }
+
+        Ok(ConversionResult::Success(value.to_bigint()))
+    }
+}
+
+impl ToJSValConvertible for AsBigInt<i64> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        bigint_to_jsval(BigIntFromInt64(cx, self.0), rval)
+    }
+}
+
+impl ToJSValConvertible for AsBigInt<u64> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        bigint_to_jsval(BigIntFromUint64(cx, self.0), rval)
+    }
+}
+
+impl ToJSValConvertible for AsBigInt<i128> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        self.0.to_jsval(cx, rval)
+    }
+}
+
+impl ToJSValConvertible for AsBigInt<u128> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        self.0.to_jsval(cx, rval)
+    }
+}
+
+macro_rules! as_bigint_from_jsval {
+    ($t:ty) => {
+        impl FromJSValConvertible for AsBigInt<$t> {
+            type Config = ConversionBehavior;
+            unsafe fn from_jsval(
+                cx: *mut JSContext,
+                value: HandleValue,
+                option: ConversionBehavior,
+            ) -> Result<ConversionResult<AsBigInt<$t>>, ()> {
+                Ok(match bigint_from_jsval(cx, value, option)? {
+                    ConversionResult::Success(v) => ConversionResult::Success(AsBigInt(v)),
+                    ConversionResult::Failure(e) => ConversionResult::Failure(e),
+                })
+            }
+        }
+    };
+}
+
+as_bigint_from_jsval!(i64);
+as_bigint_from_jsval!(u64);
+as_bigint_from_jsval!(i128);
+as_bigint_from_jsval!(u128);
+
+impl ToJSValConvertible for i128 {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        bigint_to_jsval(i128_to_bigint(cx, *self), rval)
+    }
+}
+
+impl FromJSValConvertible for i128 {
+    type Config = ConversionBehavior;
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: ConversionBehavior,
+    ) -> Result<ConversionResult<i128>, ()> {
+        bigint_from_jsval(cx, value, option)
+    }
+}
+
+impl ToJSValConvertible for u128 {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        let bigint = match i128::try_from(*self) {
+            Ok(value) => i128_to_bigint(cx, value),
+            Err(_) => {
+                let digits = self.to_string();
+                BigIntFromDecimalString(cx, digits.as_ptr() as *const libc::c_char, digits.len())
+            }
+        };
+        bigint_to_jsval(bigint, rval)
+    }
+}
+
+impl FromJSValConvertible for u128 {
+    type Config = ConversionBehavior;
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: ConversionBehavior,
+    ) -> Result<ConversionResult<u128>, ()> {
+        bigint_from_jsval(cx, value, option)
+    }
+}
+
 // https://heycam.github.io/webidl/#es-float
 impl ToJSValConvertible for f32 {
     #[inline]
@@ -549,6 +858,7 @@ impl<T: ToJSValConvertible> ToJSValConvertible for Option<T> {
 
 impl<T: FromJSValConvertible> FromJSValConvertible for Option<T> {
     type Config = T::Config;
+    const OPTIONAL: bool = true;
     unsafe fn from_jsval(
         cx: *mut JSContext,
         value: HandleValue,
@@ -618,6 +928,196 @@ impl<T: ToJSValConvertible> ToJSValConvertible for Vec<T> {
     }
 }
 
+/// Checks that `array` is an array of exactly `length` elements.
+unsafe fn check_array_length(
+    cx: *mut JSContext,
+    array: HandleObject,
+    length: u32,
+) -> Result<ConversionResult<()>, ()> {
+    let mut is_array = false;
+    if !IsArrayObject1(cx, array.into(), &mut is_array) {
+        return Err(());
+    }
+    if !is_array {
+        return Ok(ConversionResult::Failure("Value is not an array".into()));
+    }
+
+    let mut actual = 0;
+    if !GetArrayLength(cx, array.into(), &mut actual) {
+        return Err(());
+    }
+    if actual != length {
+        return Ok(ConversionResult::Failure(
+            format!(
+                "Expected an array of length {}, but got an array of length {}",
+                length, actual
+            )
+            .into(),
+        ));
+    }
+    Ok(ConversionResult::Success(()))
+}
+
+/// Converts the element at `index` of `array`. Like for `Vec`, an element
+/// that fails to convert throws a `TypeError`, which names its index.
+unsafe fn element_from_jsval<T: FromJSValConvertible>(
+    cx: *mut JSContext,
+    array: HandleObject,
+    index: u32,
+    option: T::Config,
+) -> Result<T, ()> {
+    rooted!(in(cx) let mut val = UndefinedValue());
+    if !JS_GetElement(cx, array.into(), index, val.handle_mut().into()) {
+        return Err(());
+    }
+    match T::from_jsval(cx, val.handle(), option)? {
+        ConversionResult::Success(v) => Ok(v),
+        ConversionResult::Failure(e) => {
+            throw_type_error(cx, &format!("Element {}: {}", index, e));
+            Err(())
+        }
+    }
+}
+
+/// The elements of an array converted so far, dropped if the conversion of a
+/// later element fails.
+struct PartialArray<T, const N: usize> {
+    elements: [MaybeUninit<T>; N],
+    initialized: usize,
+}
+
+impl<T, const N: usize> PartialArray<T, N> {
+    fn new() -> PartialArray<T, N> {
+        PartialArray {
+            elements: [const { MaybeUninit::uninit() }; N],
+            initialized: 0,
+        }
+    }
+
+    fn push(&mut self, element: T) {
+        self.elements[self.initialized].write(element);
+        self.initialized += 1;
+    }
+
+    /// Returns the array, which must be full.
+    unsafe fn into_array(self) -> [T; N] {
+        debug_assert_eq!(self.initialized, N);
+        let elements = ManuallyDrop::new(self);
+        ptr::read(&elements.elements as *const [MaybeUninit<T>; N] as *const [T; N])
+    }
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        for element in &mut self.elements[..self.initialized] {
+            unsafe { element.assume_init_drop() };
+        }
+    }
+}
+
+impl<T: ToJSValConvertible, const N: usize> ToJSValConvertible for [T; N] {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        <[_]>::to_jsval(self, cx, rval)
+    }
+}
+
+impl<C: Clone, T: FromJSValConvertible<Config = C>, const N: usize> FromJSValConvertible
+    for [T; N]
+{
+    type Config = C;
+
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: C,
+    ) -> Result<ConversionResult<[T; N]>, ()> {
+        if !value.is_object() {
+            return Ok(ConversionResult::Failure("Value is not an object".into()));
+        }
+
+        rooted!(in(cx) let array = value.to_object());
+        if let ConversionResult::Failure(e) = check_array_length(cx, array.handle(), N as u32)? {
+            return Ok(ConversionResult::Failure(e));
+        }
+
+        let mut elements = PartialArray::new();
+        for index in 0..N {
+            elements.push(element_from_jsval(
+                cx,
+                array.handle(),
+                index as u32,
+                option.clone(),
+            )?);
+        }
+        Ok(ConversionResult::Success(elements.into_array()))
+    }
+}
+
+// Tuples are converted to and from arrays of the same length. Each element
+// is converted with its own `Config`.
+macro_rules! impl_tuple_conversions {
+    ($len:expr; $($name:ident $index:tt),+) => {
+        impl<$($name: ToJSValConvertible),+> ToJSValConvertible for ($($name,)+) {
+            #[inline]
+            unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+                rooted!(in(cx) let js_array = NewArrayObject1(cx, $len));
+                assert!(!js_array.handle().is_null());
+
+                rooted!(in(cx) let mut val = UndefinedValue());
+                $(
+                    self.$index.to_jsval(cx, val.handle_mut());
+                    assert!(JS_DefineElement(
+                        cx,
+                        js_array.handle().into(),
+                        $index,
+                        val.handle().into(),
+                        JSPROP_ENUMERATE as u32
+                    ));
+                )+
+
+                rval.set(ObjectValue(js_array.handle().get()));
+            }
+        }
+
+        impl<$($name: FromJSValConvertible),+> FromJSValConvertible for ($($name,)+) {
+            type Config = ($($name::Config,)+);
+
+            unsafe fn from_jsval(
+                cx: *mut JSContext,
+                value: HandleValue,
+                option: Self::Config,
+            ) -> Result<ConversionResult<Self>, ()> {
+                if !value.is_object() {
+                    return Ok(ConversionResult::Failure("Value is not an object".into()));
+                }
+
+                rooted!(in(cx) let array = value.to_object());
+                if let ConversionResult::Failure(e) = check_array_length(cx, array.handle(), $len)? {
+                    return Ok(ConversionResult::Failure(e));
+                }
+
+                Ok(ConversionResult::Success(($(
+                    element_from_jsval::<$name>(cx, array.handle(), $index, option.$index)?,
+                )+)))
+            }
+        }
+    };
+}
+
+impl_tuple_conversions!(1; A 0);
+impl_tuple_conversions!(2; A 0, B 1);
+impl_tuple_conversions!(3; A 0, B 1, C 2);
+impl_tuple_conversions!(4; A 0, B 1, C 2, D 3);
+impl_tuple_conversions!(5; A 0, B 1, C 2, D 3, E 4);
+impl_tuple_conversions!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple_conversions!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple_conversions!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple_conversions!(9; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple_conversions!(10; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple_conversions!(11; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple_conversions!(12; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
 /// Rooting guard for the iterator field of ForOfIterator.
 /// Behaves like RootedGuard (roots on creation, unroots on drop),
 /// but borrows and allows access to the whole ForOfIterator, so
@@ -708,6 +1208,149 @@ impl<C: Clone, T: FromJSValConvertible<Config = C>> FromJSValConvertible for Vec
     }
 }
 
+// https://heycam.github.io/webidl/#es-record
+unsafe fn record_to_jsval<'a, T, I>(cx: *mut JSContext, entries: I, mut rval: MutableHandleValue)
+where
+    T: ToJSValConvertible + 'a,
+    I: Iterator<Item = (&'a String, &'a T)>,
+{
+    rooted!(in(cx) let js_object = JS_NewPlainObject(cx));
+    assert!(!js_object.handle().is_null());
+
+    rooted!(in(cx) let mut val = UndefinedValue());
+    for (key, value) in entries {
+        value.to_jsval(cx, val.handle_mut());
+
+        let key: Vec<u16> = key.encode_utf16().collect();
+        assert!(JS_DefineUCProperty2(
+            cx,
+            js_object.handle().into(),
+            key.as_ptr(),
+            key.len() as libc::size_t,
+            val.handle().into(),
+            JSPROP_ENUMERATE as u32
+        ));
+    }
+
+    rval.set(ObjectValue(js_object.handle().get()));
+}
+
+// https://heycam.github.io/webidl/#es-record
+unsafe fn record_from_jsval<C, T, F>(
+    cx: *mut JSContext,
+    value: HandleValue,
+    option: C,
+    mut insert: F,
+) -> Result<ConversionResult<()>, ()>
+where
+    C: Clone,
+    T: FromJSValConvertible<Config = C>,
+    F: FnMut(String, T),
+{
+    if !value.is_object() {
+        return Ok(ConversionResult::Failure("Value is not an object".into()));
+    }
+
+    rooted!(in(cx) let object = value.to_object());
+    // Own enumerable string-keyed properties, in property order.
+    let mut ids = IdVector::new(cx);
+    if !GetPropertyKeys(cx, object.handle().into(), JSITER_OWNONLY, ids.handle_mut()) {
+        return Err(());
+    }
+
+    for &id in ids.iter() {
+        rooted!(in(cx) let id = id);
+        rooted!(in(cx) let mut key = UndefinedValue());
+        if !JS_IdToValue(cx, id.get(), key.handle_mut().into()) {
+            return Err(());
+        }
+        rooted!(in(cx) let key = ToString(cx, key.handle()));
+        if key.handle().is_null() {
+            return Err(());
+        }
+        let key = jsstr_to_string(cx, key.get());
+
+        rooted!(in(cx) let mut val = UndefinedValue());
+        if !JS_GetPropertyById(
+            cx,
+            object.handle().into(),
+            id.handle().into(),
+            val.handle_mut().into(),
+        ) {
+            return Err(());
+        }
+
+        match T::from_jsval(cx, val.handle(), option.clone())? {
+            ConversionResult::Success(v) => insert(key, v),
+            ConversionResult::Failure(e) => {
+                throw_type_error(cx, &e);
+                return Err(());
+            }
+        }
+    }
+
+    Ok(ConversionResult::Success(()))
+}
+
+impl<T: ToJSValConvertible, S: BuildHasher> ToJSValConvertible for HashMap<String, T, S> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        record_to_jsval(cx, self.iter(), rval)
+    }
+}
+
+impl<C, T, S> FromJSValConvertible for HashMap<String, T, S>
+where
+    C: Clone,
+    T: FromJSValConvertible<Config = C>,
+    S: BuildHasher + Default,
+{
+    type Config = C;
+
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: C,
+    ) -> Result<ConversionResult<HashMap<String, T, S>>, ()> {
+        let mut map = HashMap::default();
+        Ok(
+            match record_from_jsval(cx, value, option, |k, v| {
+                map.insert(k, v);
+            })? {
+                ConversionResult::Success(()) => ConversionResult::Success(map),
+                ConversionResult::Failure(e) => ConversionResult::Failure(e),
+            },
+        )
+    }
+}
+
+impl<T: ToJSValConvertible> ToJSValConvertible for BTreeMap<String, T> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        record_to_jsval(cx, self.iter(), rval)
+    }
+}
+
+impl<C: Clone, T: FromJSValConvertible<Config = C>> FromJSValConvertible for BTreeMap<String, T> {
+    type Config = C;
+
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: C,
+    ) -> Result<ConversionResult<BTreeMap<String, T>>, ()> {
+        let mut map = BTreeMap::new();
+        Ok(
+            match record_from_jsval(cx, value, option, |k, v| {
+                map.insert(k, v);
+            })? {
+                ConversionResult::Success(()) => ConversionResult::Success(map),
+                ConversionResult::Failure(e) => ConversionResult::Failure(e),
+            },
+        )
+    }
+}
+
 // https://heycam.github.io/webidl/#es-object
 impl ToJSValConvertible for *mut JSObject {
     #[inline]
@@ -755,6 +1398,99 @@ impl FromJSValConvertible for *mut JSObject {
     }
 }
 
+// https://tc39.es/ecma262/#sec-date-objects
+//
+// Times outside of the range of Date convert to an invalid Date.
+impl ToJSValConvertible for SystemTime {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, mut rval: MutableHandleValue) {
+        let msec = match self.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_millis() as f64,
+            Err(e) => -(e.duration().as_millis() as f64),
+        };
+        rooted!(in(cx) let date = NewDateObjectFromMsec(cx, msec));
+        if date.handle().is_null() {
+            panic!("NewDateObject failed");
+        }
+        rval.set(ObjectValue(date.get()));
+    }
+}
+
+// https://tc39.es/ecma262/#sec-date-objects
+impl FromJSValConvertible for SystemTime {
+    type Config = ();
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        _option: (),
+    ) -> Result<ConversionResult<SystemTime>, ()> {
+        if !value.is_object() {
+            return Ok(ConversionResult::Failure("Value is not an object".into()));
+        }
+
+        // Dates from other compartments are read through their wrappers.
+        rooted!(in(cx) let date = UnwrapObjectStatic(value.to_object()));
+        if date.handle().is_null() {
+            return Ok(ConversionResult::Failure("Value is not a Date".into()));
+        }
+        let _ac = JSAutoRealm::new(cx, date.get());
+
+        let mut is_date = false;
+        if !ObjectIsDate(cx, date.handle().into(), &mut is_date) {
+            return Err(());
+        }
+        if !is_date {
+            return Ok(ConversionResult::Failure("Value is not a Date".into()));
+        }
+
+        let mut is_valid = false;
+        if !DateIsValid(cx, date.handle().into(), &mut is_valid) {
+            return Err(());
+        }
+        if !is_valid {
+            return Ok(ConversionResult::Failure("Date is invalid".into()));
+        }
+
+        let mut msec = 0.0;
+        if !DateGetMsecSinceEpoch(cx, date.handle().into(), &mut msec) {
+            return Err(());
+        }
+        let offset = Duration::from_millis(msec.abs() as u64);
+        let time = if msec < 0.0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        };
+        Ok(match time {
+            Some(time) => ConversionResult::Success(time),
+            None => ConversionResult::Failure("Date is out of the range of SystemTime".into()),
+        })
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ToJSValConvertible for DateTime<Utc> {
+    #[inline]
+    unsafe fn to_jsval(&self, cx: *mut JSContext, rval: MutableHandleValue) {
+        SystemTime::from(*self).to_jsval(cx, rval)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromJSValConvertible for DateTime<Utc> {
+    type Config = ();
+    unsafe fn from_jsval(
+        cx: *mut JSContext,
+        value: HandleValue,
+        option: (),
+    ) -> Result<ConversionResult<DateTime<Utc>>, ()> {
+        Ok(match SystemTime::from_jsval(cx, value, option)? {
+            ConversionResult::Success(time) => ConversionResult::Success(DateTime::from(time)),
+            ConversionResult::Failure(e) => ConversionResult::Failure(e),
+        })
+    }
+}
+
 impl ToJSValConvertible for *mut JS::Symbol {
     #[inline]
     unsafe fn to_jsval(&self, _: *mut JSContext, mut rval: MutableHandleValue) {
@@ -2,82 +2,768 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-//! Functions to throw JavaScript exceptions from Rust.
+//! JavaScript errors and exceptions on the Rust side.
+//!
+//! Exceptions are thrown from Rust with the `throw_*_error` functions, with
+//! `ErrorBuilder` for errors with a custom prototype, name or properties, or by
+//! returning a `JsError` from a native function. Exceptions caught while
+//! running scripts are taken as a `JSException`, which can be detached into
+//! a `DetachedException` to be sent across threads. `ErrorReport` and
+//! `ErrorNote` describe the errors and warnings the engine reports.
 
 #![deny(missing_docs)]
 
-use jsapi::{JSContext, JSErrorFormatString, JSExnType, JS_ReportErrorNumberUTF8};
-use libc;
-use std::ffi::CString;
-use std::{mem, os, ptr};
-
-/// Format string used to throw javascript errors.
-static ERROR_FORMAT_STRING_STRING: [libc::c_char; 4] = [
-    '{' as libc::c_char,
-    '0' as libc::c_char,
-    '}' as libc::c_char,
-    0 as libc::c_char,
-];
-
-/// Format string struct used to throw `TypeError`s.
-static mut TYPE_ERROR_FORMAT_STRING: JSErrorFormatString = JSErrorFormatString {
-    name: b"RUSTMSG_TYPE_ERROR\0" as *const _ as *const libc::c_char,
-    format: &ERROR_FORMAT_STRING_STRING as *const libc::c_char,
-    argCount: 1,
-    exnType: JSExnType::JSEXN_TYPEERR as i16,
-};
-
-/// Format string struct used to throw `RangeError`s.
-static mut RANGE_ERROR_FORMAT_STRING: JSErrorFormatString = JSErrorFormatString {
-    name: b"RUSTMSG_RANGE_ERROR\0" as *const _ as *const libc::c_char,
-    format: &ERROR_FORMAT_STRING_STRING as *const libc::c_char,
-    argCount: 1,
-    exnType: JSExnType::JSEXN_RANGEERR as i16,
-};
-
-/// Callback used to throw javascript errors.
-/// See throw_js_error for info about error_number.
+use conversions::jsstr_to_string;
+use glue::{GetErrorReportLinebuf, GetErrorReportNote};
+use glue::{GetErrorReportNoteCount, IsErrorReportWarning};
+use jsapi::{jsid, HandleValueArray, JSContext, JSExnType, JSObject, JS_NewUCStringCopyN};
+use jsapi::{BuildStackString, ExceptionStackOrNull, Heap, JSErrorReport, JSString, StackFormat};
+use jsapi::{CaptureCurrentStack, ExceptionStackBehavior, JS_StackCapture_AllFrames};
+use jsapi::{JSErrorBase, JS_ClearPendingException, JS_ErrorFromException};
+use jsapi::{JSErrorFormatString, JS_ReportErrorNumberUC};
+use jsapi::{JS_GetPendingException, JS_IsExceptionPending, JS_ReportOutOfMemory, Value};
+use jsval::{ObjectValue, StringValue, UndefinedValue};
+use property_key::string_id;
+use rust::wrappers::{CreateError, JS_DefinePropertyById2, JS_SetPendingException};
+use rust::wrappers::{JS_SetPrototype, NewArrayObject};
+use rust::{describe_scripted_caller, HandleObject, HandleValue, MutableHandleValue};
+use rust::{RootedTraceableBox, ToString};
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::{mem, ptr, slice};
+
+/// Creates a string holding `s`, which may contain NUL characters.
+unsafe fn new_string(cx: *mut JSContext, s: &str) -> *mut JSString {
+    let chars: Vec<u16> = s.encode_utf16().collect();
+    JS_NewUCStringCopyN(cx, chars.as_ptr(), chars.len())
+}
+
+/// Creates an error object of the given type, with the stack and location
+/// of the running script, like the engine does for its own errors.
+unsafe fn create_error(
+    cx: *mut JSContext,
+    exn_type: JSExnType,
+    message: &str,
+    rval: MutableHandleValue,
+) -> bool {
+    rooted!(in(cx) let message = new_string(cx, message));
+    if message.is_null() {
+        return false;
+    }
+
+    rooted!(in(cx) let mut stack = ptr::null_mut::<JSObject>());
+    let ref mut stack_capture = MaybeUninit::uninit();
+    JS_StackCapture_AllFrames(stack_capture.as_mut_ptr());
+    let ref mut stack_capture = stack_capture.assume_init();
+    if !CaptureCurrentStack(cx, stack.handle_mut().into(), stack_capture) {
+        return false;
+    }
+
+    let (filename, line, column) = match describe_scripted_caller(cx) {
+        Ok(caller) => (caller.filename, caller.line, caller.col),
+        Err(()) => (String::new(), 0, 0),
+    };
+    rooted!(in(cx) let filename = new_string(cx, &filename));
+    if filename.is_null() {
+        return false;
+    }
+
+    CreateError(
+        cx,
+        exn_type,
+        stack.handle(),
+        filename.handle(),
+        line,
+        column,
+        ptr::null_mut(),
+        message.handle(),
+        rval,
+    )
+}
+
+/// Builds an error object to throw, which can be an instance of a built-in
+/// error type, of a subclass of one, or an object such as a `DOMException`
+/// that carries a name and extra properties.
+///
+/// ```ignore
+/// ErrorBuilder::new(JSExnType::JSEXN_ERR, "The object can not be found here.")
+///     .prototype(dom_exception_proto.handle())
+///     .name("NotFoundError")
+///     .property("code", code.handle())
+///     .throw(cx);
+/// ```
+pub struct ErrorBuilder<'a> {
+    exn_type: JSExnType,
+    message: &'a str,
+    prototype: Option<HandleObject<'a>>,
+    name: Option<&'a str>,
+    properties: Vec<(&'a str, HandleValue<'a>)>,
+}
+
+impl<'a> ErrorBuilder<'a> {
+    /// Starts building an error of the given built-in type.
+    ///
+    /// Panics if `exn_type` is not the type of an error, as for
+    /// `throw_error`.
+    pub fn new(exn_type: JSExnType, message: &'a str) -> ErrorBuilder<'a> {
+        assert!(
+            is_error_type(exn_type),
+            "{:?} is not an error type",
+            exn_type
+        );
+        ErrorBuilder {
+            exn_type,
+            message,
+            prototype: None,
+            name: None,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Replaces the prototype of the error, usually with the prototype of a
+    /// subclass of the built-in type.
+    pub fn prototype(mut self, prototype: HandleObject<'a>) -> ErrorBuilder<'a> {
+        self.prototype = Some(prototype);
+        self
+    }
+
+    /// Defines an own `name` property, which otherwise comes from the
+    /// prototype.
+    pub fn name(mut self, name: &'a str) -> ErrorBuilder<'a> {
+        self.name = Some(name);
+        self
+    }
+
+    /// Defines an extra own property on the error. Like `message`, it is
+    /// writable, configurable and not enumerable.
+    pub fn property(mut self, name: &'a str, value: HandleValue<'a>) -> ErrorBuilder<'a> {
+        self.properties.push((name, value));
+        self
+    }
+
+    /// Stores the error in `rval`. On failure, an exception is pending.
+    pub unsafe fn build(&self, cx: *mut JSContext, mut rval: MutableHandleValue) -> Result<(), ()> {
+        rooted!(in(cx) let mut error = UndefinedValue());
+        if !create_error(cx, self.exn_type, self.message, error.handle_mut()) {
+            return Err(());
+        }
+        rooted!(in(cx) let object = error.to_object());
+
+        if let Some(prototype) = self.prototype {
+            if !JS_SetPrototype(cx, object.handle(), prototype) {
+                return Err(());
+            }
+        }
+
+        if let Some(name) = self.name {
+            rooted!(in(cx) let name = new_string(cx, name));
+            if name.is_null() {
+                return Err(());
+            }
+            rooted!(in(cx) let value = StringValue(&*name.get()));
+            define_property(cx, object.handle(), "name", value.handle())?;
+        }
+
+        for &(name, value) in &self.properties {
+            define_property(cx, object.handle(), name, value)?;
+        }
+
+        rval.set(error.get());
+        Ok(())
+    }
+
+    /// Sets the error as the pending exception of `cx`. If the error cannot
+    /// be created, the exception is the reason why, usually an out of memory
+    /// error.
+    pub unsafe fn throw(&self, cx: *mut JSContext) {
+        rooted!(in(cx) let mut error = UndefinedValue());
+        if self.build(cx, error.handle_mut()).is_ok() {
+            JS_SetPendingException(cx, error.handle(), ExceptionStackBehavior::Capture);
+        }
+    }
+}
+
+unsafe fn define_property(
+    cx: *mut JSContext,
+    object: HandleObject,
+    name: &str,
+    value: HandleValue,
+) -> Result<(), ()> {
+    rooted!(in(cx) let mut id: jsid);
+    string_id(cx, name, id.handle_mut())?;
+    if !JS_DefinePropertyById2(cx, object, id.handle(), value, 0) {
+        return Err(());
+    }
+    Ok(())
+}
+
+/// The format of the errors thrown by the `throw_*` functions, which is
+/// just the message.
+const ERROR_FORMAT: &[u8] = b"{0}\0";
+
+const fn error_format(name: &'static [u8], exn_type: JSExnType) -> JSErrorFormatString {
+    JSErrorFormatString {
+        name: name.as_ptr() as *const c_char,
+        format: ERROR_FORMAT.as_ptr() as *const c_char,
+        argCount: 1,
+        exnType: exn_type as i16,
+    }
+}
+
+struct ErrorFormats([JSErrorFormatString; 9]);
+
+// The format strings only point to static data.
+unsafe impl Sync for ErrorFormats {}
+
+/// The format of each error type that can be thrown by `throw_error`.
+static ERROR_FORMATS: ErrorFormats = ErrorFormats([
+    error_format(b"RUSTMSG_ERROR\0", JSExnType::JSEXN_ERR),
+    error_format(b"RUSTMSG_INTERNAL_ERROR\0", JSExnType::JSEXN_INTERNALERR),
+    error_format(b"RUSTMSG_AGGREGATE_ERROR\0", JSExnType::JSEXN_AGGREGATEERR),
+    error_format(b"RUSTMSG_EVAL_ERROR\0", JSExnType::JSEXN_EVALERR),
+    error_format(b"RUSTMSG_RANGE_ERROR\0", JSExnType::JSEXN_RANGEERR),
+    error_format(b"RUSTMSG_REFERENCE_ERROR\0", JSExnType::JSEXN_REFERENCEERR),
+    error_format(b"RUSTMSG_SYNTAX_ERROR\0", JSExnType::JSEXN_SYNTAXERR),
+    error_format(b"RUSTMSG_TYPE_ERROR\0", JSExnType::JSEXN_TYPEERR),
+    error_format(b"RUSTMSG_URI_ERROR\0", JSExnType::JSEXN_URIERR),
+]);
+
+/// Callback used to throw errors, whose error number is the `JSExnType` of
+/// the error.
 unsafe extern "C" fn get_error_message(
-    _user_ref: *mut os::raw::c_void,
-    error_number: libc::c_uint,
+    _user_ref: *mut c_void,
+    error_number: c_uint,
 ) -> *const JSErrorFormatString {
-    let num: JSExnType = mem::transmute(error_number);
-    match num {
-        JSExnType::JSEXN_TYPEERR => &TYPE_ERROR_FORMAT_STRING as *const JSErrorFormatString,
-        JSExnType::JSEXN_RANGEERR => &RANGE_ERROR_FORMAT_STRING as *const JSErrorFormatString,
-        _ => panic!(
-            "Bad js error number given to get_error_message: {}",
-            error_number
-        ),
-    }
-}
-
-/// Helper fn to throw a javascript error with the given message and number.
-/// Reuse the jsapi error codes to distinguish the error_number
-/// passed back to the get_error_message callback.
-/// c_uint is u32, so this cast is safe, as is casting to/from i32 from there.
-unsafe fn throw_js_error(cx: *mut JSContext, error: &str, error_number: u32) {
-    let error = CString::new(error).unwrap();
-    JS_ReportErrorNumberUTF8(
+    ERROR_FORMATS
+        .0
+        .iter()
+        .find(|format| format.exnType as c_uint == error_number)
+        .map_or(ptr::null(), |format| format)
+}
+
+/// Returns whether errors of type `exn_type` can be thrown. The other
+/// variants of `JSExnType` describe warnings, notes and engine internals.
+fn is_error_type(exn_type: JSExnType) -> bool {
+    ERROR_FORMATS
+        .0
+        .iter()
+        .any(|format| format.exnType == exn_type as i16)
+}
+
+/// Throw an error of the given built-in type with the given message.
+///
+/// Panics if `exn_type` is not the type of an error, such as `JSEXN_WARN`.
+pub unsafe fn throw_error(cx: *mut JSContext, exn_type: JSExnType, error: &str) {
+    assert!(
+        is_error_type(exn_type),
+        "{:?} is not an error type",
+        exn_type
+    );
+    if error.contains('\0') {
+        // The engine reads the message up to the first NUL.
+        return ErrorBuilder::new(exn_type, error).throw(cx);
+    }
+    let error: Vec<u16> = error.encode_utf16().chain(Some(0)).collect();
+    JS_ReportErrorNumberUC(
         cx,
         Some(get_error_message),
         ptr::null_mut(),
-        error_number,
+        exn_type as c_uint,
         error.as_ptr(),
     );
 }
 
 /// Throw a `TypeError` with the given message.
 pub unsafe fn throw_type_error(cx: *mut JSContext, error: &str) {
-    throw_js_error(cx, error, JSExnType::JSEXN_TYPEERR as u32);
+    throw_error(cx, JSExnType::JSEXN_TYPEERR, error);
 }
 
 /// Throw a `RangeError` with the given message.
 pub unsafe fn throw_range_error(cx: *mut JSContext, error: &str) {
-    throw_js_error(cx, error, JSExnType::JSEXN_RANGEERR as u32);
+    throw_error(cx, JSExnType::JSEXN_RANGEERR, error);
 }
 
 /// Throw an `InternalError` with the given message.
 pub unsafe fn throw_internal_error(cx: *mut JSContext, error: &str) {
-    throw_js_error(cx, error, JSExnType::JSEXN_INTERNALERR as u32);
+    throw_error(cx, JSExnType::JSEXN_INTERNALERR, error);
+}
+
+/// Throw a `SyntaxError` with the given message.
+pub unsafe fn throw_syntax_error(cx: *mut JSContext, error: &str) {
+    throw_error(cx, JSExnType::JSEXN_SYNTAXERR, error);
+}
+
+/// Throw a `ReferenceError` with the given message.
+pub unsafe fn throw_reference_error(cx: *mut JSContext, error: &str) {
+    throw_error(cx, JSExnType::JSEXN_REFERENCEERR, error);
+}
+
+/// Throw an `EvalError` with the given message.
+pub unsafe fn throw_eval_error(cx: *mut JSContext, error: &str) {
+    throw_error(cx, JSExnType::JSEXN_EVALERR, error);
+}
+
+/// Throw a `URIError` with the given message.
+pub unsafe fn throw_uri_error(cx: *mut JSContext, error: &str) {
+    throw_error(cx, JSExnType::JSEXN_URIERR, error);
+}
+
+/// Throw an `AggregateError` with the given message, whose `errors` property
+/// is an array of `errors`.
+pub unsafe fn throw_aggregate_error(cx: *mut JSContext, errors: &HandleValueArray, error: &str) {
+    rooted!(in(cx) let array = NewArrayObject(cx, errors));
+    if array.is_null() {
+        return;
+    }
+    rooted!(in(cx) let errors = ObjectValue(array.get()));
+    ErrorBuilder::new(JSExnType::JSEXN_AGGREGATEERR, error)
+        .property("errors", errors.handle())
+        .throw(cx);
+}
+
+/// An error returned by a Rust function called from JS, which is reported to
+/// the caller as an exception.
+///
+/// There is no conversion from `()`: a failure that may have left no
+/// exception pending would otherwise silently terminate the script, so
+/// callers say which error they mean, usually `JsError::Pending`.
+pub enum JsError {
+    /// An exception is already pending on the context.
+    Pending,
+    /// A `TypeError` with the given message.
+    TypeError(String),
+    /// A `RangeError` with the given message.
+    RangeError(String),
+    /// An arbitrary value to throw, which stays rooted until it is thrown.
+    Value(RootedTraceableBox<Heap<Value>>),
+    /// An out of memory error, reported like the engine reports its own.
+    OutOfMemory,
+    /// Terminates the running script without an exception, so that it
+    /// cannot be caught.
+    Uncatchable,
+}
+
+impl JsError {
+    /// Creates an error that throws `value`.
+    pub fn from_value(value: HandleValue) -> JsError {
+        JsError::Value(RootedTraceableBox::from_box(Heap::boxed(value.get())))
+    }
+
+    /// Sets the exception for this error as pending on `cx`. For
+    /// `Uncatchable`, any pending exception is cleared instead, so that
+    /// returning `false` to the engine terminates the script.
+    pub unsafe fn throw(self, cx: *mut JSContext) {
+        match self {
+            JsError::Pending => {}
+            JsError::TypeError(message) => throw_type_error(cx, &message),
+            JsError::RangeError(message) => throw_range_error(cx, &message),
+            JsError::Value(value) => {
+                JS_SetPendingException(cx, value.handle(), ExceptionStackBehavior::Capture)
+            }
+            JsError::OutOfMemory => JS_ReportOutOfMemory(cx),
+            JsError::Uncatchable => JS_ClearPendingException(cx),
+        }
+    }
+}
+
+/// Rethrows the value of an exception, or terminates again if it was
+/// uncatchable.
+impl From<JSException> for JsError {
+    fn from(exception: JSException) -> JsError {
+        if exception.is_uncatchable() {
+            JsError::Uncatchable
+        } else {
+            JsError::from_value(exception.value())
+        }
+    }
+}
+
+impl fmt::Debug for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsError::Pending => f.write_str("Pending"),
+            JsError::TypeError(ref message) => f.debug_tuple("TypeError").field(message).finish(),
+            JsError::RangeError(ref message) => f.debug_tuple("RangeError").field(message).finish(),
+            JsError::Value(_) => f.write_str("Value(..)"),
+            JsError::OutOfMemory => f.write_str("OutOfMemory"),
+            JsError::Uncatchable => f.write_str("Uncatchable"),
+        }
+    }
+}
+
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsError::Pending => write!(f, "pending exception"),
+            JsError::TypeError(ref message) => write!(f, "TypeError: {}", message),
+            JsError::RangeError(ref message) => write!(f, "RangeError: {}", message),
+            JsError::Value(_) => write!(f, "thrown value"),
+            JsError::OutOfMemory => write!(f, "out of memory"),
+            JsError::Uncatchable => write!(f, "uncatchable exception"),
+        }
+    }
 }
+
+impl Error for JsError {}
+
+/// A JavaScript exception taken from a context, along with the details of its
+/// error report when the thrown value is an `Error` object.
+///
+/// The thrown value stays rooted for as long as this value exists, so
+/// exceptions cannot be sent to other threads. `detach` drops the value and
+/// keeps the details, for error handling that needs `Send + Sync` errors.
+pub struct JSException {
+    value: RootedTraceableBox<Heap<Value>>,
+    uncatchable: bool,
+    timed_out: bool,
+    /// The error message, or the stringified value if a non-error was thrown.
+    pub message: String,
+    /// The file the exception was thrown from, if known.
+    pub filename: String,
+    /// The line the exception was thrown from.
+    pub line: u32,
+    /// The column the exception was thrown from.
+    pub column: u32,
+    /// The type of the error, if an `Error` object was thrown.
+    pub exn_type: Option<JSExnType>,
+    /// The formatted stack of the error, if it has one.
+    pub stack: Option<String>,
+}
+
+impl JSException {
+    fn new(value: Value, uncatchable: bool, message: String) -> JSException {
+        JSException {
+            value: RootedTraceableBox::from_box(Heap::boxed(value)),
+            uncatchable,
+            timed_out: false,
+            message,
+            filename: String::new(),
+            line: 0,
+            column: 0,
+            exn_type: None,
+            stack: None,
+        }
+    }
+
+    /// The result of a JSAPI failure without a pending exception, which
+    /// happens when execution is terminated.
+    pub(crate) fn uncatchable() -> JSException {
+        JSException::new(UndefinedValue(), true, "uncatchable exception".to_owned())
+    }
+
+    /// The result of a script being terminated for exceeding the runtime's
+    /// execution time limit.
+    pub(crate) fn timed_out() -> JSException {
+        let mut exception =
+            JSException::new(UndefinedValue(), true, "script execution timed out".to_owned());
+        exception.timed_out = true;
+        exception
+    }
+
+    /// Takes the pending exception of `cx`, clearing it. Returns `None` if no
+    /// exception is pending, which is the case after an uncatchable
+    /// termination.
+    ///
+    /// `cx` must be in a realm.
+    pub unsafe fn take(cx: *mut JSContext) -> Option<JSException> {
+        if !JS_IsExceptionPending(cx) {
+            return None;
+        }
+
+        rooted!(in(cx) let mut value = UndefinedValue());
+        let fetched = JS_GetPendingException(cx, value.handle_mut().into());
+        JS_ClearPendingException(cx);
+        if !fetched {
+            return None;
+        }
+        Some(JSException::from_value(cx, value.handle()))
+    }
+
+    /// Creates an exception from a thrown value, such as the reason a promise
+    /// was rejected with.
+    ///
+    /// `cx` must be in a realm.
+    pub unsafe fn from_value(cx: *mut JSContext, value: HandleValue) -> JSException {
+        let mut exception = JSException::new(value.get(), false, String::new());
+        let mut report = ptr::null_mut();
+        if value.is_object() {
+            rooted!(in(cx) let object = value.to_object());
+            report = JS_ErrorFromException(cx, object.handle().into());
+            exception.stack = exception_stack(cx, object.handle());
+        }
+
+        if report.is_null() {
+            exception.message = value_to_string(cx, value);
+        } else {
+            exception.read_report(&*report);
+        }
+        exception
+    }
+
+    unsafe fn read_report(&mut self, report: &JSErrorReport) {
+        let report = ErrorReport::from_raw(report);
+        self.message = report.message;
+        self.filename = report.filename;
+        self.line = report.line;
+        self.column = report.column;
+        self.exn_type = report.exn_type;
+    }
+
+    /// Returns a handle to the thrown value.
+    pub fn value(&self) -> HandleValue {
+        self.value.handle()
+    }
+
+    /// Whether the failure was caused by an uncatchable termination rather
+    /// than a thrown value. The value is `undefined` in that case.
+    pub fn is_uncatchable(&self) -> bool {
+        self.uncatchable
+    }
+
+    /// Whether the script was terminated for exceeding the execution time
+    /// limit set with `Runtime::set_execution_time_limit`. Such exceptions
+    /// are also uncatchable.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Drops the thrown value, keeping the details of the exception.
+    pub fn detach(self) -> DetachedException {
+        DetachedException {
+            uncatchable: self.uncatchable,
+            timed_out: self.timed_out,
+            message: self.message,
+            filename: self.filename,
+            line: self.line,
+            column: self.column,
+            exn_type: self.exn_type,
+            stack: self.stack,
+        }
+    }
+}
+
+/// The details of a `JSException` without the thrown value, which can be
+/// sent to other threads and outlive the runtime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetachedException {
+    uncatchable: bool,
+    timed_out: bool,
+    /// The error message, or the stringified value if a non-error was thrown.
+    pub message: String,
+    /// The file the exception was thrown from, if known.
+    pub filename: String,
+    /// The line the exception was thrown from.
+    pub line: u32,
+    /// The column the exception was thrown from.
+    pub column: u32,
+    /// The type of the error, if an `Error` object was thrown.
+    pub exn_type: Option<JSExnType>,
+    /// The formatted stack of the error, if it has one.
+    pub stack: Option<String>,
+}
+
+impl DetachedException {
+    /// Whether the failure was caused by an uncatchable termination rather
+    /// than a thrown value.
+    pub fn is_uncatchable(&self) -> bool {
+        self.uncatchable
+    }
+
+    /// Whether the script was terminated for exceeding the execution time
+    /// limit.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+impl From<JSException> for DetachedException {
+    fn from(exception: JSException) -> DetachedException {
+        exception.detach()
+    }
+}
+
+/// A `JSErrorReport` decoded into Rust types, as passed to warning reporters
+/// set with `Runtime::set_warning_reporter`.
+#[derive(Clone, Debug)]
+pub struct ErrorReport {
+    /// The error message.
+    pub message: String,
+    /// The file the error was reported in, if known.
+    pub filename: String,
+    /// The line the error was reported at.
+    pub line: u32,
+    /// The column the error was reported at.
+    pub column: u32,
+    /// The number of the error in the engine's message table.
+    pub error_number: u32,
+    /// The type of the error. `None` for warnings.
+    pub exn_type: Option<JSExnType>,
+    /// The source line the error was reported at, if the engine kept it.
+    pub source_line: Option<String>,
+    /// The offset in UTF-16 code units of the offending token in
+    /// `source_line`.
+    pub token_offset: usize,
+    /// Whether this is a warning rather than an error.
+    pub is_warning: bool,
+    /// Notes attached to the report, such as the location of a previous
+    /// declaration that conflicts with this one.
+    pub notes: Vec<ErrorNote>,
+}
+
+/// A note attached to an `ErrorReport`.
+#[derive(Clone, Debug)]
+pub struct ErrorNote {
+    /// The note's message.
+    pub message: String,
+    /// The file the note refers to, if known.
+    pub filename: String,
+    /// The line the note refers to.
+    pub line: u32,
+    /// The column the note refers to.
+    pub column: u32,
+    /// The number of the note in the engine's message table.
+    pub error_number: u32,
+}
+
+impl ErrorReport {
+    /// Decodes `report`. The report is only borrowed, so it can be decoded
+    /// from within a warning reporter or `JS_ErrorFromException`.
+    pub unsafe fn from_raw(report: &JSErrorReport) -> ErrorReport {
+        let base = ErrorNote::from_raw(&report._base);
+
+        let exn_type = report.exnType;
+        let exn_type = if exn_type >= 0 && exn_type < JSExnType::JSEXN_ERROR_LIMIT as i16 {
+            Some(mem::transmute(exn_type as u32))
+        } else {
+            None
+        };
+
+        let mut length = 0;
+        let mut token_offset = 0;
+        let linebuf = GetErrorReportLinebuf(report, &mut length, &mut token_offset);
+        let source_line = if linebuf.is_null() {
+            None
+        } else {
+            Some(String::from_utf16_lossy(slice::from_raw_parts(
+                linebuf, length,
+            )))
+        };
+
+        let notes = (0..GetErrorReportNoteCount(report))
+            .map(|i| ErrorNote::from_raw(&*GetErrorReportNote(report, i)))
+            .collect();
+
+        ErrorReport {
+            message: base.message,
+            filename: base.filename,
+            line: base.line,
+            column: base.column,
+            error_number: base.error_number,
+            exn_type,
+            source_line,
+            token_offset,
+            is_warning: IsErrorReportWarning(report),
+            notes,
+        }
+    }
+}
+
+impl ErrorNote {
+    unsafe fn from_raw(base: &JSErrorBase) -> ErrorNote {
+        let string = |chars: *const c_char| {
+            if chars.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(chars).to_string_lossy().into_owned()
+            }
+        };
+        ErrorNote {
+            message: string(base.message_.data_),
+            filename: string(base.filename),
+            line: base.lineno,
+            column: base.column,
+            error_number: base.errorNumber,
+        }
+    }
+}
+
+unsafe fn exception_stack(cx: *mut JSContext, object: HandleObject) -> Option<String> {
+    rooted!(in(cx) let stack = ExceptionStackOrNull(object.into()));
+    if stack.is_null() {
+        return None;
+    }
+
+    rooted!(in(cx) let mut string = ptr::null_mut::<JSString>());
+    if !BuildStackString(
+        cx,
+        ptr::null_mut(),
+        stack.handle().into(),
+        string.handle_mut().into(),
+        0,
+        StackFormat::Default,
+    ) {
+        JS_ClearPendingException(cx);
+        return None;
+    }
+    Some(jsstr_to_string(cx, string.get()))
+}
+
+unsafe fn value_to_string(cx: *mut JSContext, value: HandleValue) -> String {
+    rooted!(in(cx) let string = ToString(cx, value));
+    if string.is_null() {
+        // Symbols, and objects with a throwing `toString`.
+        JS_ClearPendingException(cx);
+        return "<unknown>".to_owned();
+    }
+    jsstr_to_string(cx, string.get())
+}
+
+impl fmt::Debug for JSException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JSException")
+            .field("uncatchable", &self.uncatchable)
+            .field("timed_out", &self.timed_out)
+            .field("message", &self.message)
+            .field("filename", &self.filename)
+            .field("line", &self.line)
+            .field("column", &self.column)
+            .field("exn_type", &self.exn_type)
+            .field("stack", &self.stack)
+            .finish()
+    }
+}
+
+/// Formats an exception as `filename:line:column: message`, or just the
+/// message if the location is unknown.
+fn fmt_exception(
+    f: &mut fmt::Formatter,
+    message: &str,
+    filename: &str,
+    line: u32,
+    column: u32,
+) -> fmt::Result {
+    if filename.is_empty() {
+        write!(f, "{}", message)
+    } else {
+        write!(f, "{}:{}:{}: {}", filename, line, column, message)
+    }
+}
+
+impl fmt::Display for JSException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_exception(f, &self.message, &self.filename, self.line, self.column)
+    }
+}
+
+impl Error for JSException {}
+
+impl fmt::Display for DetachedException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_exception(f, &self.message, &self.filename, self.line, self.column)
+    }
+}
+
+impl Error for DetachedException {}
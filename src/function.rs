@@ -0,0 +1,430 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Exposing Rust functions to JS.
+//!
+//! `define_function` generates the `JSNative` for a Rust function: the
+//! arguments are converted with `FromJSValConvertible` (using the default
+//! configuration of each type), the return value with `ToJSValConvertible`,
+//! and panics are caught with `wrap_panic_in`.
+//!
+//! ```ignore
+//! fn add(a: i32, b: i32) -> i32 {
+//!     a + b
+//! }
+//!
+//! define_function(cx, global.handle(), "add", add)?;
+//! ```
+//!
+//! Closures that capture state are created with `new_closure`, or with
+//! `new_traced_closure` when the state holds GC things that must be traced.
+//! The closure is stored in an object kept alive by the function, and is
+//! dropped when that object is finalized.
+
+use consts::JSCLASS_RESERVED_SLOTS_MASK;
+use conversions::{ConversionResult, FromJSValConvertible, ToJSValConvertible};
+use error::JsError;
+use glue::JS_GetReservedSlot;
+use jsapi::{jsid, CallArgs, JSClass, JSClassOps, JSContext, JSFreeOp, JSFunction, JSObject};
+use jsapi::{GetFunctionNativeReserved, NewFunctionByIdWithReserved, SetFunctionNativeReserved};
+use jsapi::{JSTracer, JS_DefineUCFunction, JS_GetFunctionObject, JS_NewObject};
+use jsapi::{JS_SetReservedSlot, Value};
+use jsapi::{JSCLASS_FOREGROUND_FINALIZE, JSCLASS_RESERVED_SLOTS_SHIFT};
+use jsval::{ObjectValue, PrivateValue, UndefinedValue};
+use panic::{wrap_panic, wrap_panic_in};
+use property_key::string_id;
+use rust::{Handle, HandleObject, MutableHandleValue, Trace};
+
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+
+/// The return type of a function exposed to JS: either a value to convert,
+/// or a `Result` whose error is thrown as an exception.
+pub trait NativeResult {
+    /// Stores the value to return to JS in `rval`.
+    unsafe fn into_rval(self, cx: *mut JSContext, rval: MutableHandleValue) -> Result<(), JsError>;
+}
+
+impl<T: ToJSValConvertible> NativeResult for T {
+    unsafe fn into_rval(self, cx: *mut JSContext, rval: MutableHandleValue) -> Result<(), JsError> {
+        self.to_jsval(cx, rval);
+        Ok(())
+    }
+}
+
+impl<T: ToJSValConvertible> NativeResult for Result<T, JsError> {
+    unsafe fn into_rval(self, cx: *mut JSContext, rval: MutableHandleValue) -> Result<(), JsError> {
+        self?.into_rval(cx, rval)
+    }
+}
+
+/// A Rust function that can be called from JS. `Args` is the tuple of its
+/// argument types.
+///
+/// This is implemented for functions of up to eight arguments, where each
+/// argument implements `FromJSValConvertible` with a `Default` configuration
+/// and the return type implements `NativeResult`. Trailing `Option`
+/// arguments may be omitted and are then `None`; calls with fewer arguments
+/// than the ones before them throw a `TypeError`.
+pub trait NativeFunction<Args>: 'static {
+    /// The number of arguments the function requires, not counting trailing
+    /// optional ones, which is also the `length` of the JS function.
+    const ARITY: u32;
+
+    /// Converts the arguments, calls the function and stores its return
+    /// value.
+    unsafe fn call(&self, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError>;
+}
+
+pub(crate) unsafe fn argument<T>(
+    cx: *mut JSContext,
+    args: &CallArgs,
+    index: u32,
+) -> Result<T, JsError>
+where
+    T: FromJSValConvertible,
+    T::Config: Default,
+{
+    let value = Handle::from_raw(args.get(index));
+    match T::from_jsval(cx, value, T::Config::default()) {
+        Ok(ConversionResult::Success(value)) => Ok(value),
+        Ok(ConversionResult::Failure(message)) => Err(JsError::TypeError(format!(
+            "Argument {}: {}",
+            index + 1,
+            message
+        ))),
+        Err(()) => Err(JsError::Pending),
+    }
+}
+
+/// A Rust function that can be called from JS, taking a reference to the
+/// state it was created with before the JS arguments. See `NativeFunction`.
+pub trait NativeClosure<T, Args>: 'static {
+    /// The number of JS arguments the function requires.
+    const ARITY: u32;
+
+    /// Converts the arguments, calls the function and stores its return
+    /// value.
+    unsafe fn call(&self, state: &T, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError>;
+}
+
+/// Returns the number of arguments before the trailing optional ones, given
+/// whether each argument is optional.
+pub(crate) const fn required_arguments(optional: &[bool]) -> u32 {
+    let mut required = optional.len();
+    while required > 0 && optional[required - 1] {
+        required -= 1;
+    }
+    required as u32
+}
+
+pub(crate) fn check_arity(args: &CallArgs, arity: u32) -> Result<(), JsError> {
+    if args.argc_ >= arity {
+        return Ok(());
+    }
+    Err(JsError::TypeError(format!(
+        "At least {} argument{} required, but only {} passed",
+        arity,
+        if arity == 1 { "" } else { "s" },
+        args.argc_
+    )))
+}
+
+macro_rules! impl_native_function {
+    ($($name:ident $arg:ident $index:expr),*) => {
+        impl<F, R, $($name,)*> NativeFunction<($($name,)*)> for F
+        where
+            F: Fn($($name),*) -> R + 'static,
+            R: NativeResult,
+            $($name: FromJSValConvertible, $name::Config: Default,)*
+        {
+            const ARITY: u32 = required_arguments(&[$($name::OPTIONAL),*]);
+
+            unsafe fn call(&self, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError> {
+                check_arity(args, required_arguments(&[$($name::OPTIONAL),*]))?;
+                $(let $arg = argument::<$name>(cx, args, $index)?;)*
+                let result = self($($arg),*);
+                result.into_rval(cx, MutableHandleValue::from_raw(args.rval()))
+            }
+        }
+
+        impl<F, T, R, $($name,)*> NativeClosure<T, ($($name,)*)> for F
+        where
+            F: Fn(&T, $($name),*) -> R + 'static,
+            R: NativeResult,
+            $($name: FromJSValConvertible, $name::Config: Default,)*
+        {
+            const ARITY: u32 = required_arguments(&[$($name::OPTIONAL),*]);
+
+            unsafe fn call(&self, state: &T, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError> {
+                check_arity(args, required_arguments(&[$($name::OPTIONAL),*]))?;
+                $(let $arg = argument::<$name>(cx, args, $index)?;)*
+                let result = self(state, $($arg),*);
+                result.into_rval(cx, MutableHandleValue::from_raw(args.rval()))
+            }
+        }
+    };
+}
+
+impl_native_function!();
+impl_native_function!(A a 0);
+impl_native_function!(A a 0, B b 1);
+impl_native_function!(A a 0, B b 1, C c 2);
+impl_native_function!(A a 0, B b 1, C c 2, D d 3);
+impl_native_function!(A a 0, B b 1, C c 2, D d 3, E e 4);
+impl_native_function!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5);
+impl_native_function!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5, H h 6);
+impl_native_function!(A a 0, B b 1, C c 2, D d 3, E e 4, G g 5, H h 6, I i 7);
+
+/// Calls `function` with panics caught, throwing the error it returns.
+/// Returns whether it succeeded, as expected from a `JSNative`. Panics are
+/// reported according to the `PanicPolicy`.
+pub(crate) unsafe fn call_native(
+    cx: *mut JSContext,
+    function: &mut dyn FnMut() -> Result<(), JsError>,
+) -> bool {
+    let mut result = false;
+    let completed = wrap_panic_in(cx, &mut || {
+        result = match function() {
+            Ok(()) => true,
+            Err(error) => {
+                error.throw(cx);
+                false
+            }
+        };
+    });
+    completed && result
+}
+
+/// Fails to compile unless `F` is zero-sized, so that it can be conjured by
+/// `zero_sized` rather than stored.
+pub(crate) fn assert_zero_sized<F>() {
+    const {
+        assert!(
+            mem::size_of::<F>() == 0,
+            "a function item or a non-capturing closure is required"
+        )
+    }
+}
+
+/// Returns a reference to a zero-sized value, which can be conjured from
+/// any well-aligned pointer.
+pub(crate) unsafe fn zero_sized<'a, F>() -> &'a F {
+    &*NonNull::<F>::dangling().as_ptr()
+}
+
+unsafe extern "C" fn trampoline<F, Args>(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool
+where
+    F: NativeFunction<Args>,
+{
+    let args = CallArgs::from_vp(vp, argc);
+    call_native(cx, &mut || zero_sized::<F>().call(cx, &args))
+}
+
+/// Defines a property `name` on `obj` holding a JS function that calls `f`.
+///
+/// `f` must be a function item or a closure that captures nothing, as it is
+/// not stored anywhere; anything else fails to compile. Use `new_closure`
+/// for closures with state.
+pub unsafe fn define_function<F, Args>(
+    cx: *mut JSContext,
+    obj: HandleObject,
+    name: &str,
+    f: F,
+) -> Result<*mut JSFunction, ()>
+where
+    F: NativeFunction<Args>,
+{
+    assert_zero_sized::<F>();
+    mem::forget(f);
+
+    let name: Vec<u16> = name.encode_utf16().collect();
+    let function = JS_DefineUCFunction(
+        cx,
+        obj.into(),
+        name.as_ptr(),
+        name.len(),
+        Some(trampoline::<F, Args>),
+        F::ARITY,
+        0,
+    );
+    if function.is_null() {
+        Err(())
+    } else {
+        Ok(function)
+    }
+}
+
+/// A closure stored in the reserved slot of a `CLOSURE_CLASS` object.
+trait ClosureData {
+    unsafe fn call(&self, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError>;
+    unsafe fn trace(&self, trc: *mut JSTracer);
+}
+
+struct Closure<F, Args> {
+    function: F,
+    marker: PhantomData<fn(Args)>,
+}
+
+impl<F: NativeFunction<Args>, Args> ClosureData for Closure<F, Args> {
+    unsafe fn call(&self, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError> {
+        self.function.call(cx, args)
+    }
+
+    unsafe fn trace(&self, _trc: *mut JSTracer) {}
+}
+
+struct TracedClosure<T, F, Args> {
+    state: T,
+    function: F,
+    marker: PhantomData<fn(Args)>,
+}
+
+impl<T: Trace, F: NativeClosure<T, Args>, Args> ClosureData for TracedClosure<T, F, Args> {
+    unsafe fn call(&self, cx: *mut JSContext, args: &CallArgs) -> Result<(), JsError> {
+        self.function.call(&self.state, cx, args)
+    }
+
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        self.state.trace(trc)
+    }
+}
+
+const CLOSURE_SLOT: u32 = 0;
+
+static CLOSURE_CLASS_OPS: JSClassOps = JSClassOps {
+    addProperty: None,
+    delProperty: None,
+    enumerate: None,
+    newEnumerate: None,
+    resolve: None,
+    mayResolve: None,
+    finalize: Some(finalize_closure),
+    call: None,
+    hasInstance: None,
+    construct: None,
+    trace: Some(trace_closure),
+};
+
+static CLOSURE_CLASS: JSClass = JSClass {
+    name: b"RustClosure\0" as *const u8 as *const _,
+    flags: JSCLASS_FOREGROUND_FINALIZE
+        | ((1 & JSCLASS_RESERVED_SLOTS_MASK) << JSCLASS_RESERVED_SLOTS_SHIFT),
+    cOps: &CLOSURE_CLASS_OPS as *const JSClassOps,
+    spec: ptr::null(),
+    ext: ptr::null(),
+    oOps: ptr::null(),
+};
+
+/// Returns the closure stored in `holder`, or null if it was not stored yet.
+unsafe fn closure_data(holder: *mut JSObject) -> *mut Box<dyn ClosureData> {
+    let mut slot = UndefinedValue();
+    JS_GetReservedSlot(holder, CLOSURE_SLOT, &mut slot);
+    if slot.is_undefined() {
+        return ptr::null_mut();
+    }
+    slot.to_private() as *mut Box<dyn ClosureData>
+}
+
+unsafe extern "C" fn finalize_closure(_fop: *mut JSFreeOp, holder: *mut JSObject) {
+    let data = closure_data(holder);
+    if !data.is_null() {
+        wrap_panic(&mut || drop(Box::from_raw(data)));
+    }
+}
+
+unsafe extern "C" fn trace_closure(trc: *mut JSTracer, holder: *mut JSObject) {
+    let data = closure_data(holder);
+    if !data.is_null() {
+        let data: &dyn ClosureData = &**data;
+        wrap_panic(&mut || data.trace(trc));
+    }
+}
+
+unsafe extern "C" fn closure_trampoline(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let holder = (*GetFunctionNativeReserved(args.callee(), 0)).to_object();
+    let data: &dyn ClosureData = &**closure_data(holder);
+    call_native(cx, &mut || data.call(cx, &args))
+}
+
+unsafe fn new_closure_function(
+    cx: *mut JSContext,
+    name: &str,
+    arity: u32,
+    data: Box<dyn ClosureData>,
+) -> Result<*mut JSFunction, ()> {
+    rooted!(in(cx) let holder = JS_NewObject(cx, &CLOSURE_CLASS));
+    if holder.is_null() {
+        return Err(());
+    }
+    let data = Box::into_raw(Box::new(data));
+    JS_SetReservedSlot(
+        holder.get(),
+        CLOSURE_SLOT,
+        &PrivateValue(data as *const c_void),
+    );
+
+    rooted!(in(cx) let mut id: jsid);
+    string_id(cx, name, id.handle_mut())?;
+
+    let function = NewFunctionByIdWithReserved(cx, Some(closure_trampoline), arity, 0, id.get());
+    if function.is_null() {
+        return Err(());
+    }
+    SetFunctionNativeReserved(
+        JS_GetFunctionObject(function),
+        0,
+        &ObjectValue(holder.get()),
+    );
+    Ok(function)
+}
+
+/// Creates a JS function named `name` that calls the closure `f`.
+///
+/// The closure is dropped when the function is collected, during GC
+/// finalization, so dropping it must not call into JS. Anything it captures
+/// is not traced; use `new_traced_closure` for state holding GC things.
+pub unsafe fn new_closure<F, Args>(
+    cx: *mut JSContext,
+    name: &str,
+    f: F,
+) -> Result<*mut JSFunction, ()>
+where
+    F: NativeFunction<Args>,
+    Args: 'static,
+{
+    let data = Closure {
+        function: f,
+        marker: PhantomData,
+    };
+    new_closure_function(cx, name, F::ARITY, Box::new(data))
+}
+
+/// Creates a JS function named `name` that calls `f` with a reference to
+/// `state`, followed by the JS arguments.
+///
+/// `state` is traced for as long as the function is alive, so it can hold
+/// `Heap` values that must stay alive with the function. Like with
+/// `new_closure`, `f` and `state` are dropped during GC finalization.
+pub unsafe fn new_traced_closure<T, F, Args>(
+    cx: *mut JSContext,
+    name: &str,
+    state: T,
+    f: F,
+) -> Result<*mut JSFunction, ()>
+where
+    T: Trace + 'static,
+    F: NativeClosure<T, Args>,
+    Args: 'static,
+{
+    let data = TracedClosure {
+        state,
+        function: f,
+        marker: PhantomData,
+    };
+    new_closure_function(cx, name, F::ARITY, Box::new(data))
+}
@@ -22,6 +22,8 @@ pub struct JobQueueTraps {
         ) -> bool,
     >,
     pub empty: ::std::option::Option<unsafe extern "C" fn(queue: *const c_void) -> bool>,
+    pub runJobs:
+        ::std::option::Option<unsafe extern "C" fn(queue: *const c_void, cx: *mut JSContext)>,
 }
 impl ::std::default::Default for JobQueueTraps {
     fn default() -> JobQueueTraps {
@@ -29,6 +31,21 @@ impl ::std::default::Default for JobQueueTraps {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct CompileOptionsParams {
+    pub filename: *const c_char,
+    pub line: u32,
+    pub column: u32,
+    pub sourceMapURL: *const u16,
+    pub introductionType: *const c_char,
+    pub forceStrictMode: bool,
+    pub noScriptRval: bool,
+    pub selfHostingMode: bool,
+    pub forceFullParse: bool,
+    pub deferDebugMetadata: bool,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Default)]
 pub struct ReadableStreamUnderlyingSourceTraps {
@@ -385,6 +402,17 @@ extern "C" {
         aLine: u32,
     ) -> *mut ReadOnlyCompileOptions;
     pub fn DeleteCompileOptions(aOpts: *mut ReadOnlyCompileOptions);
+    pub fn NewCompileOptionsWithParams(
+        aCx: *mut JSContext,
+        aParams: *const CompileOptionsParams,
+    ) -> *mut ReadOnlyCompileOptions;
+    pub fn UpdateScriptDebugMetadata(
+        aCx: *mut JSContext,
+        aScript: JS::HandleScript,
+        aOptions: *const ReadOnlyCompileOptions,
+        aPrivateValue: JS::HandleValue,
+        aElementAttributeName: JS::HandleString,
+    ) -> bool;
     pub fn NewProxyObject(
         aCx: *mut JSContext,
         aHandler: *const ::libc::c_void,
@@ -419,7 +447,25 @@ extern "C" {
     pub fn RUST_JSID_TO_STRING(id: HandleId) -> *mut JSString;
     pub fn RUST_SYMBOL_TO_JSID(sym: *mut Symbol, id: MutableHandleId);
     pub fn RUST_JSID_IS_VOID(id: HandleId) -> bool;
+    pub fn RUST_JSID_IS_SYMBOL(id: HandleId) -> bool;
+    pub fn RUST_JSID_TO_SYMBOL(id: HandleId) -> *mut Symbol;
     pub fn SetBuildId(buildId: *mut JS::BuildIdCharVector, chars: *const u8, len: usize) -> bool;
+    pub fn NewTranscodeBuffer() -> *mut JS::TranscodeBuffer;
+    pub fn DeleteTranscodeBuffer(buffer: *mut JS::TranscodeBuffer);
+    pub fn GetTranscodeBufferData(buffer: *mut JS::TranscodeBuffer, length: *mut usize) -> *const u8;
+    pub fn DecodeScriptFromBytes(
+        cx: *mut JSContext,
+        options: *const JS::ReadOnlyCompileOptions,
+        bytes: *const u8,
+        length: usize,
+        script: JS::MutableHandleScript,
+    ) -> JS::TranscodeResult;
+    pub fn BigIntFromDecimalString(
+        cx: *mut JSContext,
+        chars: *const c_char,
+        length: usize,
+    ) -> *mut JS::BigInt;
+    pub fn NewDateObjectFromMsec(cx: *mut JSContext, msecSinceEpoch: f64) -> *mut JSObject;
     pub fn RUST_SET_JITINFO(func: *mut JSFunction, info: *const JSJitInfo);
     pub fn RUST_INTERNED_STRING_TO_JSID(
         cx: *mut JSContext,
@@ -618,4 +664,12 @@ extern "C" {
         line: *mut u32,
         col: *mut u32,
     ) -> bool;
+    pub fn GetErrorReportLinebuf(
+        report: *const JSErrorReport,
+        length: *mut usize,
+        tokenOffset: *mut usize,
+    ) -> *const u16;
+    pub fn IsErrorReportWarning(report: *const JSErrorReport) -> bool;
+    pub fn GetErrorReportNoteCount(report: *const JSErrorReport) -> usize;
+    pub fn GetErrorReportNote(report: *const JSErrorReport, index: usize) -> *const JSErrorBase;
 }
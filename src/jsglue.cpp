@@ -15,8 +15,10 @@
 
 #include "jsapi.h"
 #include "jsfriendapi.h"
+#include "js/BigInt.h"
 #include "js/BuildId.h"
 #include "js/Class.h"
+#include "js/Date.h"
 #include "js/Id.h"
 #include "js/MemoryMetrics.h"
 #include "js/Modules.h"  // include for JS::GetModulePrivate
@@ -25,6 +27,7 @@
 #include "js/Proxy.h"
 #include "js/Stream.h"
 #include "js/StructuredClone.h"
+#include "js/Transcoding.h"
 #include "js/Wrapper.h"
 #include "js/friend/ErrorMessages.h"
 #include "js/experimental/JitInfo.h"
@@ -43,6 +46,7 @@ struct JobQueueTraps {
                             JS::HandleObject job, JS::HandleObject allocationSite,
                             JS::HandleObject incumbentGlobal) = 0;
   bool (*empty)(void* queue);
+  void (*runJobs)(void* queue, JSContext* cx);
 };
 
 class RustJobQueue : public JS::JobQueue{
@@ -71,7 +75,10 @@ public:
   }
 
   virtual void runJobs(JSContext* cx) {
-    MOZ_ASSERT(false, "runJobs should not be invoked");
+    MOZ_ASSERT(mTraps.runJobs, "The job queue was created without a runJobs trap");
+    if (mTraps.runJobs) {
+      mTraps.runJobs(mQueue, cx);
+    }
   }
 
 private:
@@ -81,6 +88,19 @@ private:
   }
 };
 
+struct CompileOptionsParams {
+  const char* filename;
+  unsigned line;
+  unsigned column;
+  const char16_t* sourceMapURL;
+  const char* introductionType;
+  bool forceStrictMode;
+  bool noScriptRval;
+  bool selfHostingMode;
+  bool forceFullParse;
+  bool deferDebugMetadata;
+};
+
 struct ReadableStreamUnderlyingSourceTraps {
   void (*requestData)(void* source, JSContext* cx, JS::HandleObject stream, size_t desiredSize);
   void (*writeIntoReadRequestBuffer)(void* source, JSContext* cx, JS::HandleObject stream, void* buffer, size_t length, size_t* bytesWritten);
@@ -738,6 +758,54 @@ NewCompileOptions(
     return owned;
 }
 
+JS::ReadOnlyCompileOptions*
+NewCompileOptionsWithParams(JSContext* aCx, const CompileOptionsParams* aParams)
+{
+    JS::CompileOptions opts(aCx);
+    opts.setFileAndLine(aParams->filename, aParams->line);
+    opts.setColumn(aParams->column);
+    if (aParams->sourceMapURL) {
+        opts.setSourceMapURL(aParams->sourceMapURL);
+    }
+    if (aParams->introductionType) {
+        opts.setIntroductionType(aParams->introductionType);
+    }
+    if (aParams->forceStrictMode) {
+        opts.setForceStrictMode();
+    }
+    opts.setNoScriptRval(aParams->noScriptRval);
+    opts.selfHostingMode = aParams->selfHostingMode;
+    if (aParams->forceFullParse) {
+        opts.setForceFullParse();
+    }
+    opts.setDeferDebugMetadata(aParams->deferDebugMetadata);
+
+    JS::OwningCompileOptions *owned = new JS::OwningCompileOptions(aCx);
+    if (!owned)
+    {
+        return nullptr;
+    }
+
+    if (!owned->copy(aCx, opts))
+    {
+        DeleteCompileOptions(owned);
+        return nullptr;
+    }
+
+    return owned;
+}
+
+bool
+UpdateScriptDebugMetadata(JSContext* aCx, JS::HandleScript aScript,
+                          const JS::ReadOnlyCompileOptions* aOptions,
+                          JS::HandleValue aPrivateValue,
+                          JS::HandleString aElementAttributeName)
+{
+    JS::InstantiateOptions instantiateOptions(*aOptions);
+    return JS::UpdateDebugMetadata(aCx, aScript, instantiateOptions, aPrivateValue,
+                                   aElementAttributeName, nullptr, nullptr);
+}
+
 JSObject*
 NewProxyObject(JSContext* aCx, const void* aHandler, JS::HandleValue aPriv,
                JSObject* proto, JSClass* aClass, bool aLazyProto)
@@ -845,12 +913,73 @@ RUST_JSID_IS_VOID(JS::HandleId id)
     return JSID_IS_VOID(id);
 }
 
+bool
+RUST_JSID_IS_SYMBOL(JS::HandleId id)
+{
+    return JSID_IS_SYMBOL(id);
+}
+
+JS::Symbol*
+RUST_JSID_TO_SYMBOL(JS::HandleId id)
+{
+    return JSID_TO_SYMBOL(id);
+}
+
 bool
 SetBuildId(JS::BuildIdCharVector* buildId, const char* chars, size_t len) {
     buildId->clear();
     return buildId->append(chars, len);
 }
 
+JS::TranscodeBuffer*
+NewTranscodeBuffer()
+{
+    return new JS::TranscodeBuffer();
+}
+
+void
+DeleteTranscodeBuffer(JS::TranscodeBuffer* buffer)
+{
+    delete buffer;
+}
+
+const uint8_t*
+GetTranscodeBufferData(JS::TranscodeBuffer* buffer, size_t* length)
+{
+    *length = buffer->length();
+    return buffer->begin();
+}
+
+JS::TranscodeResult
+DecodeScriptFromBytes(JSContext* cx, const JS::ReadOnlyCompileOptions* options,
+                      const uint8_t* bytes, size_t length,
+                      JS::MutableHandleScript script)
+{
+    if (!JS::IsTranscodingBytecodeAligned(bytes)) {
+        // The decoder requires aligned input; copy into a buffer that is.
+        JS::TranscodeBuffer buffer;
+        if (!buffer.append(bytes, length)) {
+            JS_ReportOutOfMemory(cx);
+            return JS::TranscodeResult::Throw;
+        }
+        return JS::DecodeScript(cx, *options, buffer, script);
+    }
+    JS::TranscodeRange range(bytes, length);
+    return JS::DecodeScript(cx, *options, range, script);
+}
+
+JS::BigInt*
+BigIntFromDecimalString(JSContext* cx, const char* chars, size_t length)
+{
+    return JS::SimpleStringToBigInt(cx, mozilla::Span<const char>(chars, length), 10);
+}
+
+JSObject*
+NewDateObjectFromMsec(JSContext* cx, double msecSinceEpoch)
+{
+    return JS::NewDateObject(cx, JS::TimeClip(msecSinceEpoch));
+}
+
 void
 RUST_SET_JITINFO(JSFunction* func, const JSJitInfo* info) {
     SET_JITINFO(func, info);
@@ -1300,4 +1429,35 @@ DescribeScriptedCaller(
   return true;
 }
 
+const char16_t*
+GetErrorReportLinebuf(const JSErrorReport* report, size_t* length, size_t* tokenOffset)
+{
+  *length = report->linebufLength();
+  *tokenOffset = report->tokenOffset();
+  return report->linebuf();
+}
+
+bool
+IsErrorReportWarning(const JSErrorReport* report)
+{
+  return report->isWarning();
+}
+
+size_t
+GetErrorReportNoteCount(const JSErrorReport* report)
+{
+  return report->notes ? report->notes->length() : 0;
+}
+
+const JSErrorBase*
+GetErrorReportNote(const JSErrorReport* report, size_t index)
+{
+  MOZ_ASSERT(index < GetErrorReportNoteCount(report));
+  auto note = report->notes->begin();
+  for (size_t i = 0; i < index; i++) {
+    ++note;
+  }
+  return (*note).get();
+}
+
 } // extern "C"
@@ -28,13 +28,20 @@
 //! [4]: https://github.com/mozilla-spidermonkey/spidermonkey-embedding-examples/
 //!
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
 #[macro_use]
 extern crate lazy_static;
 extern crate libc;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "derive")]
+extern crate mozjs_derive;
 extern crate mozjs_sys;
 extern crate num_traits;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde as serde_crate;
 
 pub mod jsapi {
     pub use mozjs_sys::jsapi::glue::*;
@@ -52,10 +59,20 @@ pub mod jsapi {
 pub mod rust;
 
 mod consts;
+pub mod class;
+pub mod collections;
 pub mod conversions;
 pub mod error;
+pub mod function;
 pub mod glue;
+pub mod module;
 pub mod panic;
+pub mod promise;
+pub mod property_key;
+pub mod proxy;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod slots;
 pub mod typedarray;
 
 pub use consts::*;
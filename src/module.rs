@@ -0,0 +1,426 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Loading and evaluating ES modules.
+//!
+//! A `ModuleLoader` maps import specifiers to module URLs and fetches their
+//! source. Once installed with `Runtime::set_module_loader`, it serves both
+//! static `import` declarations and dynamic `import()` calls, and compiled
+//! modules are cached by URL for the lifetime of the runtime.
+
+use conversions::{jsstr_to_string, ToJSValConvertible};
+use error::{throw_internal_error, throw_type_error, JSException};
+use jsapi::{CompileModule1, DynamicImportStatus, FinishDynamicModuleImport, GetModuleNamespace};
+use jsapi::{FinishDynamicModuleImport_NoTLA, Handle as RawHandle, HandleObject as RawHandleObject};
+use jsapi::{HandleValue as RawHandleValue, Heap, JSAutoRealm, JSContext, JSObject, JSString};
+use jsapi::{JSTracer, ModuleEvaluate, ModuleInstantiate, SetModuleDynamicImportHook};
+use jsapi::{SetModulePrivate, SetModuleResolveHook};
+use jsval::UndefinedValue;
+use panic::{maybe_resume_unwind, wrap_panic_in};
+use promise::{block_on, JSPromise, PromiseResult, Stalled};
+use rust::{transform_str_to_source_text, CompileOptionsWrapper, HandleObject};
+use rust::{RootedTraceableBox, Runtime, Trace};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::ptr;
+use std::rc::Rc;
+
+/// Resolves and fetches modules for a runtime.
+///
+/// Module URLs are opaque strings chosen by the loader; they identify a
+/// module in the runtime's module map and are passed back as the referrer
+/// when resolving the module's own imports. Top-level imports of a URL that
+/// is already in the module map use that module without resolving. Errors
+/// are thrown to script as `TypeError`s with the returned message.
+pub trait ModuleLoader {
+    /// Resolves `specifier`, imported from the module at `referrer` or from
+    /// top-level code if `referrer` is `None`, to a module URL.
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, String>;
+
+    /// Returns the source of the module at `url`.
+    fn fetch(&self, url: &str) -> Result<String, String>;
+}
+
+/// Loads modules from the filesystem. Relative specifiers resolve against
+/// the importing module's directory, and bare specifiers against the root
+/// directory. Module URLs are canonical paths.
+///
+/// Only files under the root can be loaded: absolute specifiers, and
+/// specifiers that resolve outside of the root, including through symbolic
+/// links, are rejected.
+pub struct FilesystemLoader {
+    root: PathBuf,
+}
+
+impl FilesystemLoader {
+    /// Creates a loader resolving bare and top-level specifiers against `root`.
+    /// Fails if `root` cannot be canonicalized, for example because it does
+    /// not exist.
+    pub fn new<P: AsRef<Path>>(root: P) -> io::Result<FilesystemLoader> {
+        Ok(FilesystemLoader {
+            root: fs::canonicalize(root)?,
+        })
+    }
+}
+
+impl ModuleLoader for FilesystemLoader {
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, String> {
+        if Path::new(specifier).has_root() {
+            return Err(format!("{} is not relative to the root", specifier));
+        }
+        let base = match referrer {
+            Some(referrer) if is_relative(specifier) => {
+                Path::new(referrer).parent().map_or(self.root.clone(), Path::to_path_buf)
+            }
+            _ => self.root.clone(),
+        };
+        let path = base.join(specifier);
+        let path = match fs::canonicalize(&path) {
+            Ok(path) => path,
+            Err(error) => return Err(format!("cannot find module {}: {}", path.display(), error)),
+        };
+        if !path.starts_with(&self.root) {
+            return Err(format!("{} points outside of the root", specifier));
+        }
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    fn fetch(&self, url: &str) -> Result<String, String> {
+        if !Path::new(url).starts_with(&self.root) {
+            return Err(format!("{} points outside of the root", url));
+        }
+        fs::read_to_string(url).map_err(|error| format!("cannot read module {}: {}", url, error))
+    }
+}
+
+/// Serves modules from an in-memory map of URLs to sources. Relative
+/// specifiers resolve against the `/`-separated URL of the importing module;
+/// all other specifiers are used as URLs directly.
+#[derive(Clone, Debug, Default)]
+pub struct MapLoader {
+    modules: HashMap<String, String>,
+}
+
+impl MapLoader {
+    /// Creates an empty loader.
+    pub fn new() -> MapLoader {
+        MapLoader::default()
+    }
+
+    /// Adds or replaces the module at `url`.
+    pub fn insert<U: Into<String>, S: Into<String>>(&mut self, url: U, source: S) {
+        self.modules.insert(url.into(), source.into());
+    }
+}
+
+impl ModuleLoader for MapLoader {
+    fn resolve(&self, specifier: &str, referrer: Option<&str>) -> Result<String, String> {
+        let url = match referrer {
+            Some(referrer) if is_relative(specifier) => {
+                let directory = referrer.rfind('/').map_or("", |index| &referrer[..index]);
+                normalize(Path::new(directory).join(specifier))?
+            }
+            _ => specifier.to_owned(),
+        };
+        if self.modules.contains_key(&url) {
+            Ok(url)
+        } else {
+            Err(format!("cannot find module {}", url))
+        }
+    }
+
+    fn fetch(&self, url: &str) -> Result<String, String> {
+        self.modules
+            .get(url)
+            .cloned()
+            .ok_or_else(|| format!("cannot find module {}", url))
+    }
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+/// Removes `.` and `..` components without touching the filesystem. Fails
+/// if a `..` would leave the root.
+fn normalize(path: PathBuf) -> Result<String, String> {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match components.last() {
+                Some(&"") | None => {
+                    return Err(format!("{} points outside of the root", path.display()))
+                }
+                Some(_) => {
+                    components.pop();
+                }
+            },
+            Component::RootDir => components.push(""),
+            component => components.push(component.as_os_str().to_str().unwrap()),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+// ___________________________________________________________________________
+// Module map
+
+struct ModuleMap {
+    loader: Rc<dyn ModuleLoader>,
+    modules: RefCell<HashMap<String, Box<Heap<*mut JSObject>>>>,
+}
+
+unsafe impl Trace for ModuleMap {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        for module in self.modules.borrow().values() {
+            module.trace(trc);
+        }
+    }
+}
+
+thread_local!(static MODULE_MAP: RefCell<Option<RootedTraceableBox<ModuleMap>>> = RefCell::new(None));
+
+fn loader() -> Rc<dyn ModuleLoader> {
+    MODULE_MAP.with(|map| {
+        map.borrow()
+            .as_ref()
+            .expect("No module loader was set for this runtime.")
+            .loader
+            .clone()
+    })
+}
+
+fn cached_module(url: &str) -> *mut JSObject {
+    MODULE_MAP.with(|map| {
+        map.borrow()
+            .as_ref()
+            .and_then(|map| map.modules.borrow().get(url).map(|module| module.get()))
+            .unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Drops the module map of the current thread, which must happen while the
+/// runtime is still alive.
+pub(crate) fn shut_down_module_loader() {
+    let map = MODULE_MAP.with(|map| map.borrow_mut().take());
+    drop(map);
+}
+
+/// Compiles `source` as the module at `url` and adds it to the module map.
+/// Returns null with an exception pending on failure.
+unsafe fn compile_module(cx: *mut JSContext, url: &str, source: &str) -> *mut JSObject {
+    let options = CompileOptionsWrapper::new(cx, url, 1);
+    let mut source = transform_str_to_source_text(source);
+    rooted!(in(cx) let module = CompileModule1(cx, options.ptr, &mut source));
+    if module.is_null() {
+        return ptr::null_mut();
+    }
+
+    rooted!(in(cx) let mut private = UndefinedValue());
+    url.to_jsval(cx, private.handle_mut());
+    SetModulePrivate(module.get(), &*private);
+
+    MODULE_MAP.with(|map| {
+        let map = map.borrow();
+        let map = map.as_ref().expect("No module loader was set for this runtime.");
+        map.modules
+            .borrow_mut()
+            .insert(url.to_owned(), Heap::boxed(module.get()));
+    });
+    module.get()
+}
+
+/// Returns the module at `url`, fetching and compiling it if it is not in
+/// the module map yet. Returns null with an exception pending on failure.
+unsafe fn fetch_module(cx: *mut JSContext, url: &str) -> *mut JSObject {
+    let module = cached_module(url);
+    if !module.is_null() {
+        return module;
+    }
+    match loader().fetch(url) {
+        Ok(source) => compile_module(cx, url, &source),
+        Err(message) => {
+            throw_type_error(cx, &message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Resolves and fetches the module imported with `specifier` by the module
+/// or script whose private value is `referrer`.
+unsafe fn import_module(
+    cx: *mut JSContext,
+    referrer: RawHandleValue,
+    specifier: RawHandle<*mut JSString>,
+) -> *mut JSObject {
+    let specifier = jsstr_to_string(cx, specifier.get());
+    let referrer = if referrer.is_string() {
+        Some(jsstr_to_string(cx, referrer.to_string()))
+    } else {
+        let module = cached_module(&specifier);
+        if !module.is_null() {
+            return module;
+        }
+        None
+    };
+    match loader().resolve(&specifier, referrer.as_ref().map(|referrer| &**referrer)) {
+        Ok(url) => fetch_module(cx, &url),
+        Err(message) => {
+            throw_type_error(cx, &message);
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn module_resolve_hook(
+    cx: *mut JSContext,
+    referencing_private: RawHandleValue,
+    specifier: RawHandle<*mut JSString>,
+) -> *mut JSObject {
+    let mut module = ptr::null_mut();
+    wrap_panic_in(cx, &mut || {
+        module = import_module(cx, referencing_private, specifier)
+    });
+    module
+}
+
+unsafe extern "C" fn module_dynamic_import_hook(
+    cx: *mut JSContext,
+    referencing_private: RawHandleValue,
+    specifier: RawHandle<*mut JSString>,
+    promise: RawHandleObject,
+) -> bool {
+    let mut result = false;
+    wrap_panic_in(cx, &mut || {
+        rooted!(in(cx) let module = import_module(cx, referencing_private, specifier));
+        rooted!(in(cx) let mut evaluation = UndefinedValue());
+        let evaluated = !module.is_null() &&
+            ModuleInstantiate(cx, module.handle().into()) &&
+            ModuleEvaluate(cx, module.handle().into(), evaluation.handle_mut().into());
+        result = if evaluated && evaluation.is_object() {
+            rooted!(in(cx) let evaluation = evaluation.to_object());
+            FinishDynamicModuleImport(
+                cx,
+                evaluation.handle().into(),
+                referencing_private,
+                specifier,
+                promise,
+            )
+        } else {
+            let status = if evaluated {
+                DynamicImportStatus::Ok
+            } else {
+                DynamicImportStatus::Failed
+            };
+            FinishDynamicModuleImport_NoTLA(cx, status, referencing_private, specifier, promise)
+        };
+    });
+    result
+}
+
+impl Runtime {
+    /// Installs `loader` to serve the static and dynamic imports of all
+    /// modules and scripts of this runtime. Modules loaded by a previous
+    /// loader are discarded.
+    pub fn set_module_loader<L: ModuleLoader + 'static>(&self, loader: L) {
+        shut_down_module_loader();
+        let map = RootedTraceableBox::new(ModuleMap {
+            loader: Rc::new(loader),
+            modules: RefCell::new(HashMap::new()),
+        });
+        MODULE_MAP.with(|slot| *slot.borrow_mut() = Some(map));
+        unsafe {
+            SetModuleResolveHook(self.rt(), Some(module_resolve_hook));
+            SetModuleDynamicImportHook(self.rt(), Some(module_dynamic_import_hook));
+        }
+    }
+
+    /// Loads the module `specifier` resolves to from top-level code,
+    /// evaluates it along with its dependencies in the realm of `glob`, and
+    /// returns its namespace object.
+    ///
+    /// Runs the executor of the current thread until evaluation completes,
+    /// including any top-level `await`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no module loader was set.
+    pub fn evaluate_module(
+        &self,
+        glob: HandleObject,
+        specifier: &str,
+    ) -> Result<RootedTraceableBox<Heap<*mut JSObject>>, JSException> {
+        let cx = self.cx();
+        let _ac = JSAutoRealm::new(cx, glob.get());
+        let url = loader().resolve(specifier, None).map_err(|message| unsafe {
+            throw_type_error(cx, &message);
+            self.take_exception()
+        })?;
+        let module = unsafe { fetch_module(cx, &url) };
+        self.evaluate_compiled_module(module)
+    }
+
+    /// Like `evaluate_module`, but compiles `source` as the module at `url`
+    /// instead of loading it. Imports of `url` by other modules receive this
+    /// module.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no module loader was set.
+    pub fn evaluate_module_source(
+        &self,
+        glob: HandleObject,
+        url: &str,
+        source: &str,
+    ) -> Result<RootedTraceableBox<Heap<*mut JSObject>>, JSException> {
+        let cx = self.cx();
+        let _ac = JSAutoRealm::new(cx, glob.get());
+        let module = unsafe { compile_module(cx, url, source) };
+        self.evaluate_compiled_module(module)
+    }
+
+    /// Evaluates `module` in the current realm and returns its namespace.
+    fn evaluate_compiled_module(
+        &self,
+        module: *mut JSObject,
+    ) -> Result<RootedTraceableBox<Heap<*mut JSObject>>, JSException> {
+        let cx = self.cx();
+        unsafe {
+            rooted!(in(cx) let module = module);
+            rooted!(in(cx) let mut evaluation = UndefinedValue());
+            let evaluated = !module.is_null() &&
+                ModuleInstantiate(cx, module.handle().into()) &&
+                ModuleEvaluate(cx, module.handle().into(), evaluation.handle_mut().into());
+            maybe_resume_unwind();
+            if !evaluated {
+                return Err(self.take_exception());
+            }
+            if evaluation.is_object() {
+                rooted!(in(cx) let evaluation = evaluation.to_object());
+                if let Some(promise) = JSPromise::from_object(evaluation.handle()) {
+                    settle(self, promise)?;
+                }
+            }
+
+            let namespace = GetModuleNamespace(cx, module.handle().into());
+            if namespace.is_null() {
+                return Err(self.take_exception());
+            }
+            Ok(RootedTraceableBox::from_box(Heap::boxed(namespace)))
+        }
+    }
+}
+
+/// Runs the executor until `promise` settles. The context must be in a realm.
+fn settle(runtime: &Runtime, promise: JSPromise) -> PromiseResult {
+    block_on(runtime, promise).unwrap_or_else(|Stalled| unsafe {
+        throw_internal_error(runtime.cx(), "The module's evaluation never settled");
+        Err(runtime.take_exception())
+    })
+}
@@ -2,16 +2,62 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use error::throw_internal_error;
+use jsapi::{JSContext, JS_ClearPendingException};
 use std::any::Any;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::thread;
 
-thread_local!(static PANIC_PAYLOAD: RefCell<Option<Box<dyn Any + Send>>> = RefCell::new(None));
+thread_local!(static PANIC_PAYLOADS: RefCell<Vec<Box<dyn Any + Send>>> = RefCell::new(Vec::new()));
+thread_local!(static CALLBACK_DEPTH: Cell<usize> = Cell::new(0));
+thread_local!(static PANIC_POLICY: Cell<PanicPolicy> = Cell::new(PanicPolicy::Terminate));
+
+/// How a panic caught by `wrap_panic_in` is reported to the JS code that
+/// called into Rust. Either way, the panic resumes once control returns to
+/// Rust.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Terminate the running script with an uncatchable exception, so that
+    /// no more JS runs before the panic resumes. This is the default.
+    Terminate,
+    /// Throw an `InternalError` carrying the panic message. Scripts can
+    /// catch it and keep running until control returns to Rust.
+    InternalError,
+}
+
+/// Sets the panic policy of the current thread.
+pub fn set_panic_policy(policy: PanicPolicy) {
+    PANIC_POLICY.with(|current| current.set(policy));
+}
+
+/// Returns the panic policy of the current thread.
+pub fn panic_policy() -> PanicPolicy {
+    PANIC_POLICY.with(|current| current.get())
+}
+
+/// The payload of a resumed panic when several panics were caught before
+/// control returned to Rust, in the order they happened.
+pub struct Panics(pub Vec<Box<dyn Any + Send>>);
 
 /// If there is a pending panic, resume unwinding.
+///
+/// The entry points of this crate that run JS, such as
+/// `Runtime::evaluate_script`, `Runtime::run_jobs` and the module APIs, call
+/// this before returning, so a panic caught in a callback resumes once the
+/// engine returns to them. Code that runs JS through raw JSAPI calls should
+/// call it afterwards. Within callbacks this does nothing, so that JS code
+/// still on the stack unwinds first.
 pub fn maybe_resume_unwind() {
-    if let Some(error) = PANIC_PAYLOAD.with(|result| result.borrow_mut().take()) {
-        resume_unwind(error);
+    // Resuming while already unwinding would abort.
+    if thread::panicking() || CALLBACK_DEPTH.with(|depth| depth.get()) > 0 {
+        return;
+    }
+    let mut payloads = PANIC_PAYLOADS.with(|payloads| payloads.replace(Vec::new()));
+    match payloads.len() {
+        0 => {}
+        1 => resume_unwind(payloads.pop().unwrap()),
+        _ => resume_unwind(Box::new(Panics(payloads))),
     }
 }
 
@@ -19,14 +65,56 @@ pub fn maybe_resume_unwind() {
 // https://github.com/servo/servo/issues/26585
 #[inline(never)]
 pub fn wrap_panic(function: &mut dyn FnMut()) {
-    match catch_unwind(AssertUnwindSafe(function)) {
-        Ok(()) => {}
-        Err(payload) => {
-            PANIC_PAYLOAD.with(|opt_payload| {
-                let mut opt_payload = opt_payload.borrow_mut();
-                assert!(opt_payload.is_none());
-                *opt_payload = Some(payload);
-            });
+    if let Err(payload) = catch_callback(function) {
+        store_payload(payload);
+    }
+}
+
+/// Like `wrap_panic`, for callbacks that report failure to the engine. A
+/// caught panic is reported to `cx` according to the current
+/// `PanicPolicy`. Returns `false` if `function` panicked.
+#[inline(never)]
+pub unsafe fn wrap_panic_in(cx: *mut JSContext, function: &mut dyn FnMut()) -> bool {
+    let payload = match catch_callback(function) {
+        Ok(()) => return true,
+        Err(payload) => payload,
+    };
+    match panic_policy() {
+        PanicPolicy::Terminate => JS_ClearPendingException(cx),
+        PanicPolicy::InternalError => {
+            let message = format!("Rust code panicked: {}", panic_message(&*payload));
+            throw_internal_error(cx, &message);
+        }
+    }
+    store_payload(payload);
+    false
+}
+
+fn catch_callback(function: &mut dyn FnMut()) -> Result<(), Box<dyn Any + Send>> {
+    CALLBACK_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = catch_unwind(AssertUnwindSafe(function));
+    CALLBACK_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    result
+}
+
+/// Stores `payload` to be resumed by `maybe_resume_unwind`. Panics caught
+/// before the previous ones resumed are kept rather than lost.
+fn store_payload(payload: Box<dyn Any + Send>) {
+    PANIC_PAYLOADS.with(|payloads| {
+        let mut payloads = payloads.borrow_mut();
+        match payload.downcast::<Panics>() {
+            Ok(panics) => payloads.extend(panics.0),
+            Err(payload) => payloads.push(payload),
         }
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        *message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<Any>"
     }
 }
@@ -0,0 +1,441 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Bridging between JS Promises and Rust futures.
+//!
+//! `JSPromise` can be awaited from Rust, and `future_to_promise` exposes a
+//! Rust future to JS as a Promise. Both are driven by a single-threaded
+//! executor that runs on the runtime's thread: futures are polled by
+//! `block_on` and `run_until_stalled`, which also perform microtask
+//! checkpoints so that Promise reactions make progress.
+//!
+//! Futures woken from outside the engine, for example by another thread, a
+//! timer or a channel, must hold a `WakeSource` while they wait, so that
+//! `block_on` keeps waiting for them instead of failing with `Stalled`.
+
+use error::JSException;
+use glue::JS_GetPromiseResult;
+use jsapi::{GetPromiseState, IsPromiseObject, JSAutoRealm, JSContext};
+use jsapi::{Heap, JSObject, NewPromiseObject, PromiseState, RejectPromise, ResolvePromise};
+use jsapi::Value;
+use jsval::UndefinedValue;
+use rust::{HandleObject, HandleValue, RootedTraceableBox, Runtime};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// The outcome of a settled promise: the fulfillment value, or the rejection
+/// reason as an exception.
+pub type PromiseResult = Result<RootedTraceableBox<Heap<Value>>, JSException>;
+
+/// A rooted handle to a JS Promise object.
+///
+/// Awaiting a `JSPromise` resolves once the promise settles, which only
+/// happens while the executor of the current thread is running.
+pub struct JSPromise {
+    inner: Rc<PromiseInner>,
+}
+
+struct PromiseInner {
+    object: RootedTraceableBox<Heap<*mut JSObject>>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl JSPromise {
+    /// Creates a new pending promise in the current realm.
+    pub unsafe fn new(cx: *mut JSContext) -> Result<JSPromise, ()> {
+        let object = NewPromiseObject(cx, HandleObject::null().into());
+        if object.is_null() {
+            return Err(());
+        }
+        Ok(JSPromise::from_raw(object))
+    }
+
+    /// Wraps an existing object, returning `None` if it is not a promise.
+    pub unsafe fn from_object(object: HandleObject) -> Option<JSPromise> {
+        if object.is_null() || !IsPromiseObject(object.into()) {
+            return None;
+        }
+        Some(JSPromise::from_raw(object.get()))
+    }
+
+    unsafe fn from_raw(object: *mut JSObject) -> JSPromise {
+        JSPromise {
+            inner: Rc::new(PromiseInner {
+                object: RootedTraceableBox::from_box(Heap::boxed(object)),
+                wakers: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Returns a handle to the promise object.
+    pub fn handle(&self) -> HandleObject {
+        self.inner.object.handle()
+    }
+
+    /// Returns the current state of the promise.
+    pub fn state(&self) -> PromiseState {
+        unsafe { GetPromiseState(self.handle().into()) }
+    }
+
+    /// Resolves the promise with `value`, which may itself be a thenable.
+    pub unsafe fn resolve(&self, cx: *mut JSContext, value: HandleValue) -> Result<(), ()> {
+        let _ac = JSAutoRealm::new(cx, self.handle().get());
+        if ResolvePromise(cx, self.handle().into(), value.into()) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Rejects the promise with `reason`.
+    pub unsafe fn reject(&self, cx: *mut JSContext, reason: HandleValue) -> Result<(), ()> {
+        let _ac = JSAutoRealm::new(cx, self.handle().get());
+        if RejectPromise(cx, self.handle().into(), reason.into()) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    unsafe fn settle(&self, cx: *mut JSContext, result: PromiseResult) {
+        let _ac = JSAutoRealm::new(cx, self.handle().get());
+        let settled = match result {
+            Ok(value) => self.resolve(cx, value.handle()),
+            Err(exception) => self.reject(cx, exception.value()),
+        };
+        if settled.is_err() {
+            if let Some(exception) = JSException::take(cx) {
+                warn!("Failed to settle promise: {}", exception);
+            }
+        }
+    }
+}
+
+impl Clone for JSPromise {
+    fn clone(&self) -> JSPromise {
+        JSPromise {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Future for JSPromise {
+    type Output = PromiseResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<PromiseResult> {
+        let state = self.state();
+        if state == PromiseState::Pending {
+            let mut wakers = self.inner.wakers.borrow_mut();
+            if wakers.is_empty() {
+                let weak = Rc::downgrade(&self.inner);
+                PENDING_PROMISES.with(|pending| pending.borrow_mut().push(weak));
+            }
+            // Clones of the promise may be awaited by several tasks.
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+
+        unsafe {
+            let js_cx = Runtime::get();
+            let _ac = JSAutoRealm::new(js_cx, self.handle().get());
+            rooted!(in(js_cx) let mut result = UndefinedValue());
+            JS_GetPromiseResult(self.handle().into(), result.handle_mut().into());
+            if state == PromiseState::Fulfilled {
+                Poll::Ready(Ok(RootedTraceableBox::from_box(Heap::boxed(result.get()))))
+            } else {
+                Poll::Ready(Err(JSException::from_value(js_cx, result.handle())))
+            }
+        }
+    }
+}
+
+thread_local!(static PENDING_PROMISES: RefCell<Vec<Weak<PromiseInner>>> = RefCell::new(Vec::new()));
+
+/// Wakes the tasks awaiting promises that have settled since they were
+/// polled. Called after every microtask checkpoint.
+pub(crate) fn wake_settled_promises() {
+    let pending = PENDING_PROMISES.with(|pending| pending.replace(Vec::new()));
+    let mut still_pending = Vec::with_capacity(pending.len());
+    for weak in pending {
+        let inner = match weak.upgrade() {
+            Some(inner) => inner,
+            None => continue,
+        };
+        if unsafe { GetPromiseState(inner.object.handle().into()) } == PromiseState::Pending {
+            still_pending.push(weak);
+        } else {
+            let wakers = inner.wakers.replace(Vec::new());
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+    PENDING_PROMISES.with(|pending| pending.borrow_mut().extend(still_pending));
+}
+
+// ___________________________________________________________________________
+// Executor
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+struct Executor {
+    tasks: HashMap<usize, Task>,
+    wakers: HashMap<usize, Arc<TaskWaker>>,
+    next_id: usize,
+    ready: Arc<ReadyQueue>,
+}
+
+/// Wake-ups of the executor of one thread, which may come from any thread.
+struct ReadyQueue {
+    tasks: Mutex<VecDeque<usize>>,
+    main: AtomicBool,
+    thread: Thread,
+    /// The number of live `WakeSource`s.
+    sources: AtomicUsize,
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<ReadyQueue>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.tasks.lock().unwrap().push_back(self.id);
+        self.ready.thread.unpark();
+    }
+}
+
+struct MainWaker {
+    ready: Arc<ReadyQueue>,
+}
+
+impl Wake for MainWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.main.store(true, Ordering::SeqCst);
+        self.ready.thread.unpark();
+    }
+}
+
+thread_local!(static EXECUTOR: RefCell<Executor> = RefCell::new(Executor {
+    tasks: HashMap::new(),
+    wakers: HashMap::new(),
+    next_id: 0,
+    ready: Arc::new(ReadyQueue {
+        tasks: Mutex::new(VecDeque::new()),
+        main: AtomicBool::new(false),
+        thread: thread::current(),
+        sources: AtomicUsize::new(0),
+    }),
+}));
+
+/// Announces that a future waiting on the executor of the current thread
+/// may be woken from outside the JS engine. While any `WakeSource` of a
+/// thread exists, `block_on` parks the thread instead of failing with
+/// `Stalled` when nothing else can make progress.
+///
+/// ```ignore
+/// let source = WakeSource::new();
+/// let waker = cx.waker().clone();
+/// thread::spawn(move || {
+///     do_work();
+///     waker.wake();
+///     drop(source);
+/// });
+/// ```
+pub struct WakeSource {
+    ready: Arc<ReadyQueue>,
+}
+
+impl WakeSource {
+    /// Creates a wake source for the executor of the current thread.
+    pub fn new() -> WakeSource {
+        let ready = EXECUTOR.with(|executor| executor.borrow().ready.clone());
+        ready.sources.fetch_add(1, Ordering::SeqCst);
+        WakeSource { ready }
+    }
+}
+
+impl Default for WakeSource {
+    fn default() -> WakeSource {
+        WakeSource::new()
+    }
+}
+
+impl Drop for WakeSource {
+    fn drop(&mut self) {
+        self.ready.sources.fetch_sub(1, Ordering::SeqCst);
+        // Let a parked `block_on` notice that it may have stalled.
+        self.ready.thread.unpark();
+    }
+}
+
+/// Spawns a future on the executor of the current thread. It is polled the
+/// next time the executor runs.
+pub fn spawn_local<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        let id = executor.next_id;
+        executor.next_id += 1;
+        let waker = Arc::new(TaskWaker {
+            id,
+            ready: executor.ready.clone(),
+        });
+        executor.tasks.insert(id, Box::pin(future));
+        executor.wakers.insert(id, waker);
+        executor.ready.tasks.lock().unwrap().push_back(id);
+    });
+}
+
+/// Returns a promise, created in the current realm, that settles with the
+/// output of `future` once the executor has run it to completion.
+pub unsafe fn future_to_promise<F>(cx: *mut JSContext, future: F) -> Result<JSPromise, ()>
+where
+    F: Future<Output = PromiseResult> + 'static,
+{
+    let promise = JSPromise::new(cx)?;
+    spawn_local(SettlePromise {
+        promise: promise.clone(),
+        future: Box::pin(future),
+    });
+    Ok(promise)
+}
+
+struct SettlePromise<F: Future<Output = PromiseResult>> {
+    promise: JSPromise,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future<Output = PromiseResult>> Future for SettlePromise<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        match self.future.as_mut().poll(cx) {
+            Poll::Ready(result) => {
+                unsafe { self.promise.settle(Runtime::get(), result) };
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Polls every ready task once. Returns whether any task was polled.
+fn poll_ready_tasks() -> bool {
+    let ready: Vec<usize> =
+        EXECUTOR.with(|executor| executor.borrow().ready.tasks.lock().unwrap().drain(..).collect());
+    let polled = !ready.is_empty();
+    for id in ready {
+        // Tasks may spawn further tasks, so don't hold the borrow while polling.
+        let task = EXECUTOR.with(|executor| {
+            let mut executor = executor.borrow_mut();
+            let task = executor.tasks.remove(&id)?;
+            Some((task, executor.wakers[&id].clone()))
+        });
+        let (mut task, waker) = match task {
+            Some(task) => task,
+            None => continue,
+        };
+        let waker = Waker::from(waker);
+        let pending = task
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending();
+        EXECUTOR.with(|executor| {
+            let mut executor = executor.borrow_mut();
+            if pending {
+                executor.tasks.insert(id, task);
+            } else {
+                executor.wakers.remove(&id);
+            }
+        });
+    }
+    polled
+}
+
+fn has_ready_tasks() -> bool {
+    EXECUTOR.with(|executor| !executor.borrow().ready.tasks.lock().unwrap().is_empty())
+}
+
+/// Runs spawned tasks and Promise jobs until neither can make progress
+/// without an external wake-up.
+///
+/// # Panics
+///
+/// Panics if the runtime was built without the built-in job queue.
+pub fn run_until_stalled(runtime: &Runtime) {
+    loop {
+        let polled = poll_ready_tasks();
+        let had_jobs = runtime.has_pending_jobs();
+        // Also wakes tasks awaiting promises settled directly from Rust.
+        runtime.run_jobs();
+        if !polled && !had_jobs && !has_ready_tasks() {
+            break;
+        }
+    }
+}
+
+/// The error returned by `block_on` when its future can never complete:
+/// nothing can make progress, and no `WakeSource` exists that could wake it
+/// or the spawned tasks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stalled;
+
+/// Runs `future` to completion on the current thread, running spawned tasks
+/// and Promise jobs in the meantime. Parks the thread while nothing can make
+/// progress, as long as a `WakeSource` exists; otherwise fails with
+/// `Stalled`.
+///
+/// # Panics
+///
+/// Panics if the runtime was built without the built-in job queue.
+pub fn block_on<F: Future>(runtime: &Runtime, future: F) -> Result<F::Output, Stalled> {
+    let ready = EXECUTOR.with(|executor| executor.borrow().ready.clone());
+    let main = Arc::new(MainWaker {
+        ready: ready.clone(),
+    });
+    let mut future = Box::pin(future);
+    ready.main.store(true, Ordering::SeqCst);
+    loop {
+        if ready.main.swap(false, Ordering::SeqCst) {
+            let waker = Waker::from(main.clone());
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return Ok(output);
+            }
+        }
+        run_until_stalled(runtime);
+        if !ready.main.load(Ordering::SeqCst) && ready.tasks.lock().unwrap().is_empty() {
+            if ready.sources.load(Ordering::SeqCst) == 0 {
+                return Err(Stalled);
+            }
+            thread::park();
+        }
+    }
+}
+
+/// Drops the tasks and promise registrations of the current thread, which
+/// must happen while the runtime is still alive.
+pub(crate) fn shut_down_executor() {
+    let tasks = EXECUTOR.with(|executor| {
+        let mut executor = executor.borrow_mut();
+        executor.ready.tasks.lock().unwrap().clear();
+        executor.wakers.clear();
+        executor.tasks.drain().collect::<Vec<_>>()
+    });
+    drop(tasks);
+    PENDING_PROMISES.with(|pending| pending.borrow_mut().clear());
+}
@@ -0,0 +1,244 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Property keys owned by Rust.
+//!
+//! A `jsid` is only valid while rooted, and can only be inspected through
+//! the glue functions. A `PropertyKey` holds the same information in Rust
+//! types, and converts back to a `jsid` when a property needs to be
+//! accessed.
+//!
+//! ```ignore
+//! let mut ids = IdVector::new(cx);
+//! GetPropertyKeys(cx, obj, JSITER_OWNONLY | JSITER_SYMBOLS, ids.handle_mut());
+//! for key in ids.keys(cx) {
+//!     println!("{}", key);
+//! }
+//! ```
+
+use conversions::jsstr_to_string;
+use glue::{int_to_jsid, RUST_INTERNED_STRING_TO_JSID, RUST_SYMBOL_TO_JSID};
+use glue::{RUST_JSID_IS_INT, RUST_JSID_IS_STRING, RUST_JSID_IS_SYMBOL};
+use glue::{RUST_JSID_TO_INT, RUST_JSID_TO_STRING, RUST_JSID_TO_SYMBOL};
+use jsapi::{jsid, GetSymbolDescription, Heap, JSContext, JSTracer};
+use jsapi::{JS_AtomizeAndPinUCStringN, JS_NewUCStringCopyN, JS_StringToId};
+use rust::{Handle, HandleId, HandleSymbol, MutableHandleId, Trace};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::slice;
+
+/// The largest index stored as an integer `jsid`. Larger indices are stored
+/// as strings.
+const JSID_INT_MAX: u32 = i32::max_value() as u32;
+
+/// The key of a property.
+pub enum PropertyKey {
+    /// An array index stored as an integer.
+    Index(u32),
+    /// Any other string key, including indices that are too large to be
+    /// stored as integers.
+    String(String),
+    /// A symbol key.
+    Symbol(SymbolKey),
+}
+
+/// A symbol used as a property key.
+///
+/// The symbol is held by a `Heap`, so a key that lives across a GC must be
+/// traced, for example by storing it in a `RootedTraceableBox`.
+pub struct SymbolKey {
+    id: Box<Heap<jsid>>,
+    description: Option<String>,
+}
+
+impl SymbolKey {
+    /// Creates a key for `symbol`.
+    pub unsafe fn new(cx: *mut JSContext, symbol: HandleSymbol) -> SymbolKey {
+        rooted!(in(cx) let mut id: jsid);
+        RUST_SYMBOL_TO_JSID(symbol.get(), id.handle_mut().into());
+        let description = GetSymbolDescription(symbol.into());
+        SymbolKey {
+            id: Heap::boxed(id.get()),
+            description: if description.is_null() {
+                None
+            } else {
+                Some(jsstr_to_string(cx, description))
+            },
+        }
+    }
+
+    /// Returns the description of the symbol, if it has one.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_ref().map(|description| &**description)
+    }
+}
+
+impl PropertyKey {
+    /// Reads the key stored in `id`, which must not be void.
+    pub unsafe fn from_id(cx: *mut JSContext, id: HandleId) -> PropertyKey {
+        if RUST_JSID_IS_INT(id.into()) {
+            return PropertyKey::Index(RUST_JSID_TO_INT(id.into()) as u32);
+        }
+        if RUST_JSID_IS_STRING(id.into()) {
+            return PropertyKey::String(jsstr_to_string(cx, RUST_JSID_TO_STRING(id.into())));
+        }
+        assert!(RUST_JSID_IS_SYMBOL(id.into()), "void jsid");
+        rooted!(in(cx) let symbol = RUST_JSID_TO_SYMBOL(id.into()));
+        PropertyKey::Symbol(SymbolKey::new(cx, symbol.handle()))
+    }
+
+    /// Stores the `jsid` for this key in `id`.
+    pub unsafe fn to_id(&self, cx: *mut JSContext, mut id: MutableHandleId) -> Result<(), ()> {
+        match *self {
+            PropertyKey::Index(index) if index <= JSID_INT_MAX => {
+                int_to_jsid(index as i32, id.into());
+                Ok(())
+            }
+            PropertyKey::Index(index) => string_id(cx, &index.to_string(), id),
+            PropertyKey::String(ref name) => string_id(cx, name, id),
+            PropertyKey::Symbol(ref symbol) => {
+                id.set(symbol.id.get());
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<u32> for PropertyKey {
+    fn from(index: u32) -> PropertyKey {
+        PropertyKey::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for PropertyKey {
+    fn from(name: &'a str) -> PropertyKey {
+        PropertyKey::String(name.to_owned())
+    }
+}
+
+impl From<String> for PropertyKey {
+    fn from(name: String) -> PropertyKey {
+        PropertyKey::String(name)
+    }
+}
+
+/// Formats the key like `String(key)` does in JS.
+impl fmt::Display for PropertyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PropertyKey::Index(index) => write!(f, "{}", index),
+            PropertyKey::String(ref name) => f.write_str(name),
+            PropertyKey::Symbol(ref symbol) => {
+                write!(f, "Symbol({})", symbol.description().unwrap_or(""))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PropertyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PropertyKey::Index(index) => f.debug_tuple("Index").field(&index).finish(),
+            PropertyKey::String(ref name) => f.debug_tuple("String").field(name).finish(),
+            PropertyKey::Symbol(ref symbol) => {
+                f.debug_tuple("Symbol").field(&symbol.description).finish()
+            }
+        }
+    }
+}
+
+unsafe impl Trace for SymbolKey {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        self.id.trace(trc);
+    }
+}
+
+unsafe impl Trace for PropertyKey {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        if let PropertyKey::Symbol(ref symbol) = *self {
+            symbol.trace(trc);
+        }
+    }
+}
+
+/// Stores the id of the property named `name` in `id`.
+pub(crate) unsafe fn string_id(
+    cx: *mut JSContext,
+    name: &str,
+    id: MutableHandleId,
+) -> Result<(), ()> {
+    let chars: Vec<u16> = name.encode_utf16().collect();
+    rooted!(in(cx) let name = JS_NewUCStringCopyN(cx, chars.as_ptr(), chars.len()));
+    if name.is_null() || !JS_StringToId(cx, name.handle().into(), id.into()) {
+        return Err(());
+    }
+    Ok(())
+}
+
+thread_local!(static INTERNED: RefCell<HashMap<String, jsid>> = RefCell::new(HashMap::new()));
+
+/// Stores the id of the property named `name` in `id`, like
+/// `PropertyKey::to_id`, but pins the string for the lifetime of the runtime
+/// and caches its id. This avoids creating a new string for names that are
+/// used over and over again.
+pub unsafe fn interned_id(
+    cx: *mut JSContext,
+    name: &str,
+    mut id: MutableHandleId,
+) -> Result<(), ()> {
+    if let Some(cached) = INTERNED.with(|interned| interned.borrow().get(name).cloned()) {
+        id.set(cached);
+        return Ok(());
+    }
+
+    let chars: Vec<u16> = name.encode_utf16().collect();
+    let atom = JS_AtomizeAndPinUCStringN(cx, chars.as_ptr(), chars.len());
+    if atom.is_null() {
+        return Err(());
+    }
+    rooted!(in(cx) let mut interned: jsid);
+    RUST_INTERNED_STRING_TO_JSID(cx, atom, interned.handle_mut().into());
+    INTERNED.with(|cache| cache.borrow_mut().insert(name.to_owned(), interned.get()));
+    id.set(interned.get());
+    Ok(())
+}
+
+/// Forgets the interned ids of the current thread, whose strings are only
+/// pinned while the runtime is alive.
+pub(crate) fn shut_down_interned_ids() {
+    INTERNED.with(|interned| interned.borrow_mut().clear());
+}
+
+/// An iterator over the keys of an `IdVector`, returned by `IdVector::keys`.
+pub struct PropertyKeys<'a> {
+    cx: *mut JSContext,
+    ids: slice::Iter<'a, jsid>,
+}
+
+impl<'a> PropertyKeys<'a> {
+    pub(crate) unsafe fn new(cx: *mut JSContext, ids: &'a [jsid]) -> PropertyKeys<'a> {
+        PropertyKeys {
+            cx,
+            ids: ids.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for PropertyKeys<'a> {
+    type Item = PropertyKey;
+
+    fn next(&mut self) -> Option<PropertyKey> {
+        let cx = self.cx;
+        // The ids are rooted by the vector.
+        self.ids
+            .next()
+            .map(|id| unsafe { PropertyKey::from_id(cx, Handle::from_marked_location(id)) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
@@ -0,0 +1,728 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Proxy objects whose behaviour is implemented in Rust.
+//!
+//! A proxy created by `new_proxy` owns a `ProxyHandler` and a target object.
+//! Every trap the handler does not override forwards the operation to the
+//! target, like an empty handler object does for a JS `Proxy`.
+//!
+//! ```ignore
+//! struct Constant(i32);
+//!
+//! unsafe impl Trace for Constant {
+//!     unsafe fn trace(&self, _: *mut JSTracer) {}
+//! }
+//!
+//! impl ProxyHandler for Constant {
+//!     unsafe fn get(
+//!         &self,
+//!         _cx: *mut JSContext,
+//!         _proxy: HandleObject,
+//!         _receiver: HandleValue,
+//!         _id: HandleId,
+//!         mut vp: MutableHandleValue,
+//!     ) -> Result<(), JsError> {
+//!         vp.set(Int32Value(self.0));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! rooted!(in(cx) let proxy = new_proxy(cx, Constant(42), target.handle(), proto.handle())?);
+//! ```
+//!
+//! Traps run with panics caught: a panic makes the operation fail and is
+//! resumed once control returns to Rust, as with `wrap_panic`.
+//!
+//! A few traps of SpiderMonkey proxies cannot be overridden:
+//!
+//! * `getPrototype` and `setPrototype`: the prototype passed to `new_proxy`
+//!   is stored on the proxy like that of an ordinary object.
+//! * `getOwnEnumerablePropertyKeys`: it filters the keys from
+//!   `own_property_keys` through `get_own_property_descriptor`.
+
+use conversions::ToJSValConvertible;
+use error::{throw_internal_error, JsError};
+use function::call_native;
+use glue::{CreateProxyHandler, GetProxyPrivate, GetProxyReservedSlot, NewProxyObject};
+use glue::{ProxyTraps, SetProxyReservedSlot};
+use jsapi::Handle as RawHandle;
+use jsapi::HandleId as RawHandleId;
+use jsapi::HandleObject as RawHandleObject;
+use jsapi::HandleValue as RawHandleValue;
+use jsapi::MutableHandle as RawMutableHandle;
+use jsapi::MutableHandleIdVector as RawMutableHandleIdVector;
+use jsapi::MutableHandleObject as RawMutableHandleObject;
+use jsapi::MutableHandleValue as RawMutableHandleValue;
+use jsapi::{CallArgs, HandleValueArray, JSContext, JSFreeOp, JSObject, JSString, JSTracer};
+use jsapi::{IsCallable, IsConstructor, ObjectOpResult, PropertyDescriptor};
+use jsapi::{JSITER_HIDDEN, JSITER_OWNONLY, JSITER_SYMBOLS};
+use jsval::{ObjectValue, PrivateValue, UndefinedValue};
+use panic::wrap_panic;
+use rust::wrappers::{AppendToIdVector, Call, Construct, GetPropertyKeys, JS_DefinePropertyById};
+use rust::wrappers::{JS_DeletePropertyById, JS_ForwardGetPropertyTo, JS_ForwardSetPropertyTo};
+use rust::wrappers::{JS_GetOwnPropertyDescriptorById, JS_GetPrototype, JS_HasPropertyById};
+use rust::wrappers::{JS_HasInstance, JS_IsExtensible, JS_PreventExtensions};
+use rust::{Handle, HandleId, HandleObject, HandleValue, MutableHandle, MutableHandleObject};
+use rust::{MutableHandleValue, Trace};
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+/// The behaviour of a proxy object. Each method implements one trap of the
+/// proxy, and by default forwards the operation to the target of the proxy.
+///
+/// Errors are thrown as JS exceptions. Traps that receive an
+/// `ObjectOpResult` report a failure that is not an exception, such as a
+/// read-only property, through it instead.
+pub trait ProxyHandler: Trace + 'static {
+    /// Finds the own property `id` of the proxy. The descriptor is left
+    /// untouched if there is no such property.
+    unsafe fn get_own_property_descriptor(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+        desc: MutableHandle<PropertyDescriptor>,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_GetOwnPropertyDescriptorById(
+            cx,
+            target.handle(),
+            id,
+            desc,
+        ))
+    }
+
+    /// Defines the own property `id` of the proxy.
+    unsafe fn define_property(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+        desc: Handle<PropertyDescriptor>,
+        result: &mut ObjectOpResult,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_DefinePropertyById(cx, target.handle(), id, desc, result))
+    }
+
+    /// Appends the keys of all own properties of the proxy to `props`,
+    /// including non-enumerable and symbol keys.
+    unsafe fn own_property_keys(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        props: &mut PropertyKeysOut,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        props.append_own_keys(cx, target.handle())
+    }
+
+    /// Deletes the own property `id` of the proxy.
+    unsafe fn delete(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+        result: &mut ObjectOpResult,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_DeletePropertyById(cx, target.handle(), id, result))
+    }
+
+    /// Makes the proxy non-extensible.
+    unsafe fn prevent_extensions(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        result: &mut ObjectOpResult,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_PreventExtensions(cx, target.handle(), result))
+    }
+
+    /// Returns whether properties can be added to the proxy.
+    unsafe fn is_extensible(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+    ) -> Result<bool, JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        let mut extensible = false;
+        check(JS_IsExtensible(cx, target.handle(), &mut extensible))?;
+        Ok(extensible)
+    }
+
+    /// Returns whether the proxy or its prototype chain has the property
+    /// `id`, as for the `in` operator.
+    unsafe fn has(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+    ) -> Result<bool, JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        let mut found = false;
+        check(JS_HasPropertyById(cx, target.handle(), id, &mut found))?;
+        Ok(found)
+    }
+
+    /// Returns whether the proxy has the own property `id`. By default, this
+    /// asks `get_own_property_descriptor`.
+    unsafe fn has_own(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+    ) -> Result<bool, JsError> {
+        rooted!(in(cx) let mut desc: PropertyDescriptor);
+        self.get_own_property_descriptor(cx, proxy, id, desc.handle_mut())?;
+        Ok(!desc.obj.is_null())
+    }
+
+    /// Gets the value of the property `id`, with `receiver` as the `this`
+    /// value of getters.
+    unsafe fn get(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        receiver: HandleValue,
+        id: HandleId,
+        vp: MutableHandleValue,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_ForwardGetPropertyTo(
+            cx,
+            target.handle(),
+            id,
+            receiver,
+            vp,
+        ))
+    }
+
+    /// Sets the property `id` to `v`, with `receiver` as the `this` value of
+    /// setters.
+    unsafe fn set(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        id: HandleId,
+        v: HandleValue,
+        receiver: HandleValue,
+        result: &mut ObjectOpResult,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        check(JS_ForwardSetPropertyTo(
+            cx,
+            target.handle(),
+            id,
+            v,
+            receiver,
+            result,
+        ))
+    }
+
+    /// Returns whether the proxy can be called. This is only queried, and
+    /// must not run any JS.
+    unsafe fn is_callable(&self, proxy: *mut JSObject) -> bool {
+        IsCallable(get_proxy_target(proxy))
+    }
+
+    /// Returns whether the proxy can be used with `new`. This is only
+    /// queried, and must not run any JS.
+    unsafe fn is_constructor(&self, proxy: *mut JSObject) -> bool {
+        IsConstructor(get_proxy_target(proxy))
+    }
+
+    /// Calls the proxy as a function. Only used if `is_callable` is true.
+    unsafe fn call(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        args: &CallArgs,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = ObjectValue(get_proxy_target(proxy.get())));
+        check(Call(
+            cx,
+            Handle::from_raw(args.thisv()),
+            target.handle(),
+            &arguments(args),
+            MutableHandleValue::from_raw(args.rval()),
+        ))
+    }
+
+    /// Calls the proxy as a constructor. Only used if `is_constructor` is
+    /// true.
+    unsafe fn construct(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        args: &CallArgs,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = ObjectValue(get_proxy_target(proxy.get())));
+        rooted!(in(cx) let new_target = (*args.argv_.offset(args.argc_ as isize)).to_object());
+        rooted!(in(cx) let mut object = ptr::null_mut::<JSObject>());
+        check(Construct(
+            cx,
+            target.handle(),
+            new_target.handle(),
+            &arguments(args),
+            object.handle_mut(),
+        ))?;
+        MutableHandleValue::from_raw(args.rval()).set(ObjectValue(object.get()));
+        Ok(())
+    }
+
+    /// Returns whether `v` is an instance of the proxy, for
+    /// `JS_HasInstance`.
+    unsafe fn has_instance(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        v: HandleValue,
+    ) -> Result<bool, JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        let mut found = false;
+        check(JS_HasInstance(cx, target.handle(), v, &mut found))?;
+        Ok(found)
+    }
+
+    /// Returns the class name of the proxy, as shown in error messages and
+    /// by the debugger. This must not run any JS.
+    unsafe fn class_name(&self, _cx: *mut JSContext, proxy: HandleObject) -> &'static CStr {
+        if self.is_callable(proxy.get()) {
+            CStr::from_bytes_with_nul(b"Function\0").unwrap()
+        } else {
+            CStr::from_bytes_with_nul(b"Object\0").unwrap()
+        }
+    }
+
+    /// Returns the source text of the proxy for
+    /// `Function.prototype.toString`. By default, callable proxies are
+    /// native code and other proxies throw a `TypeError`.
+    unsafe fn fun_to_string(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        _is_to_string: bool,
+    ) -> Result<*mut JSString, JsError> {
+        if !self.is_callable(proxy.get()) {
+            return Err(JsError::TypeError(
+                "Function.prototype.toString called on incompatible object".to_owned(),
+            ));
+        }
+        rooted!(in(cx) let mut source = UndefinedValue());
+        "function () {\n    [native code]\n}".to_jsval(cx, source.handle_mut());
+        Ok(source.to_string())
+    }
+}
+
+/// The vector of property keys filled in by `ProxyHandler::own_property_keys`.
+pub struct PropertyKeysOut<'a> {
+    props: RawMutableHandleIdVector,
+    _marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> PropertyKeysOut<'a> {
+    /// Appends `id` to the keys.
+    pub unsafe fn append(&mut self, id: HandleId) -> Result<(), JsError> {
+        if !AppendToIdVector(self.props, id) {
+            return Err(JsError::OutOfMemory);
+        }
+        Ok(())
+    }
+
+    /// Appends the keys of all own properties of `object`, including
+    /// non-enumerable and symbol keys.
+    pub unsafe fn append_own_keys(
+        &mut self,
+        cx: *mut JSContext,
+        object: HandleObject,
+    ) -> Result<(), JsError> {
+        check(GetPropertyKeys(
+            cx,
+            object,
+            JSITER_OWNONLY | JSITER_HIDDEN | JSITER_SYMBOLS,
+            self.props,
+        ))
+    }
+}
+
+/// The reserved slot of the proxy holding its boxed handler. The private
+/// slot holds the target.
+const HANDLER_SLOT: u32 = 0;
+
+thread_local!(static HANDLERS: RefCell<HashMap<TypeId, usize>> = RefCell::new(HashMap::new()));
+
+/// Returns the C++ proxy handler dispatching to `H`, which is created on
+/// first use and lives until the process exits.
+fn handler_of<H: ProxyHandler>() -> *const c_void {
+    HANDLERS.with(|handlers| {
+        *handlers
+            .borrow_mut()
+            .entry(TypeId::of::<H>())
+            .or_insert_with(|| {
+                let traps = ProxyTraps {
+                    getOwnPropertyDescriptor: Some(get_own_property_descriptor::<H>),
+                    defineProperty: Some(define_property::<H>),
+                    ownPropertyKeys: Some(own_property_keys::<H>),
+                    delete_: Some(delete::<H>),
+                    getPrototypeIfOrdinary: Some(get_prototype_if_ordinary),
+                    preventExtensions: Some(prevent_extensions::<H>),
+                    isExtensible: Some(is_extensible::<H>),
+                    has: Some(has::<H>),
+                    hasOwn: Some(has_own::<H>),
+                    get: Some(get::<H>),
+                    set: Some(set::<H>),
+                    call: Some(call::<H>),
+                    construct: Some(construct::<H>),
+                    hasInstance: Some(has_instance::<H>),
+                    className: Some(class_name::<H>),
+                    fun_toString: Some(fun_to_string::<H>),
+                    trace: Some(trace::<H>),
+                    finalize: Some(finalize::<H>),
+                    isCallable: Some(is_callable::<H>),
+                    isConstructor: Some(is_constructor::<H>),
+                    ..ProxyTraps::default()
+                };
+                unsafe { CreateProxyHandler(&traps, ptr::null()) as usize }
+            }) as *const c_void
+    })
+}
+
+/// Creates a proxy for `target` whose traps are implemented by `handler`,
+/// with the prototype `proto`.
+///
+/// The handler is owned by the proxy: it is traced along with it and dropped
+/// when the proxy is finalized.
+pub unsafe fn new_proxy<H: ProxyHandler>(
+    cx: *mut JSContext,
+    handler: H,
+    target: HandleObject,
+    proto: HandleObject,
+) -> Result<*mut JSObject, ()> {
+    rooted!(in(cx) let private = ObjectValue(target.get()));
+    let proxy = NewProxyObject(
+        cx,
+        handler_of::<H>(),
+        private.handle().into(),
+        proto.get(),
+        ptr::null(),
+        false,
+    );
+    if proxy.is_null() {
+        return Err(());
+    }
+    let handler = Box::into_raw(Box::new(handler));
+    SetProxyReservedSlot(proxy, HANDLER_SLOT, &PrivateValue(handler as *const c_void));
+    Ok(proxy)
+}
+
+/// Returns the target of a proxy created by `new_proxy`.
+pub unsafe fn get_proxy_target(proxy: *mut JSObject) -> *mut JSObject {
+    let mut target = UndefinedValue();
+    GetProxyPrivate(proxy, &mut target);
+    target.to_object()
+}
+
+/// Returns the handler of `proxy`, or null while the proxy is being
+/// created.
+unsafe fn handler_ptr<H: ProxyHandler>(proxy: *mut JSObject) -> *mut H {
+    let mut slot = UndefinedValue();
+    GetProxyReservedSlot(proxy, HANDLER_SLOT, &mut slot);
+    if slot.is_undefined() {
+        return ptr::null_mut();
+    }
+    slot.to_private() as *mut H
+}
+
+fn check(ok: bool) -> Result<(), JsError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(JsError::Pending)
+    }
+}
+
+unsafe fn arguments(args: &CallArgs) -> HandleValueArray {
+    HandleValueArray {
+        length_: args.argc_ as usize,
+        elements_: args.argv_,
+    }
+}
+
+/// Runs a trap of the handler of `proxy`, as a `JSNative` would. A proxy
+/// without a handler, which can only be seen while it is being created,
+/// throws an `InternalError`.
+unsafe fn run_trap<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    trap: &mut dyn FnMut(&H, HandleObject) -> Result<(), JsError>,
+) -> bool {
+    let proxy = Handle::from_raw(proxy);
+    call_native(cx, &mut || {
+        let handler = handler_ptr::<H>(proxy.get());
+        if handler.is_null() {
+            throw_internal_error(cx, "Proxy trap called before its handler was set");
+            return Err(JsError::Pending);
+        }
+        trap(&*handler, proxy)
+    })
+}
+
+unsafe extern "C" fn get_own_property_descriptor<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    desc: RawMutableHandle<PropertyDescriptor>,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.get_own_property_descriptor(
+            cx,
+            proxy,
+            Handle::from_raw(id),
+            MutableHandle::from_raw(desc),
+        )
+    })
+}
+
+unsafe extern "C" fn define_property<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    desc: RawHandle<PropertyDescriptor>,
+    result: *mut ObjectOpResult,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.define_property(
+            cx,
+            proxy,
+            Handle::from_raw(id),
+            Handle::from_raw(desc),
+            &mut *result,
+        )
+    })
+}
+
+unsafe extern "C" fn own_property_keys<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    props: RawMutableHandleIdVector,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        let mut props = PropertyKeysOut {
+            props,
+            _marker: PhantomData,
+        };
+        handler.own_property_keys(cx, proxy, &mut props)
+    })
+}
+
+unsafe extern "C" fn delete<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    result: *mut ObjectOpResult,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.delete(cx, proxy, Handle::from_raw(id), &mut *result)
+    })
+}
+
+/// Proxies created by `new_proxy` have a static prototype, which the engine
+/// reads without asking the handler; this only answers for completeness.
+unsafe extern "C" fn get_prototype_if_ordinary(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    is_ordinary: *mut bool,
+    protop: RawMutableHandleObject,
+) -> bool {
+    *is_ordinary = true;
+    JS_GetPrototype(
+        cx,
+        Handle::from_raw(proxy),
+        MutableHandleObject::from_raw(protop),
+    )
+}
+
+unsafe extern "C" fn prevent_extensions<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    result: *mut ObjectOpResult,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.prevent_extensions(cx, proxy, &mut *result)
+    })
+}
+
+unsafe extern "C" fn is_extensible<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    succeeded: *mut bool,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        *succeeded = handler.is_extensible(cx, proxy)?;
+        Ok(())
+    })
+}
+
+unsafe extern "C" fn has<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    bp: *mut bool,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        *bp = handler.has(cx, proxy, Handle::from_raw(id))?;
+        Ok(())
+    })
+}
+
+unsafe extern "C" fn has_own<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    bp: *mut bool,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        *bp = handler.has_own(cx, proxy, Handle::from_raw(id))?;
+        Ok(())
+    })
+}
+
+unsafe extern "C" fn get<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    receiver: RawHandleValue,
+    id: RawHandleId,
+    vp: RawMutableHandleValue,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.get(
+            cx,
+            proxy,
+            Handle::from_raw(receiver),
+            Handle::from_raw(id),
+            MutableHandle::from_raw(vp),
+        )
+    })
+}
+
+unsafe extern "C" fn set<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    id: RawHandleId,
+    v: RawHandleValue,
+    receiver: RawHandleValue,
+    result: *mut ObjectOpResult,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.set(
+            cx,
+            proxy,
+            Handle::from_raw(id),
+            Handle::from_raw(v),
+            Handle::from_raw(receiver),
+            &mut *result,
+        )
+    })
+}
+
+unsafe extern "C" fn call<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    args: *const CallArgs,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.call(cx, proxy, &*args)
+    })
+}
+
+unsafe extern "C" fn construct<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    args: *const CallArgs,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        handler.construct(cx, proxy, &*args)
+    })
+}
+
+unsafe extern "C" fn has_instance<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    v: RawMutableHandleValue,
+    bp: *mut bool,
+) -> bool {
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        *bp = handler.has_instance(cx, proxy, MutableHandle::from_raw(v).handle())?;
+        Ok(())
+    })
+}
+
+/// Class names cannot fail, so a panicking or missing handler gives the name
+/// of a plain object.
+unsafe extern "C" fn class_name<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+) -> *const c_char {
+    let proxy = Handle::from_raw(proxy);
+    let handler = handler_ptr::<H>(proxy.get());
+    let mut name = b"Object\0".as_ptr() as *const c_char;
+    if !handler.is_null() {
+        wrap_panic(&mut || name = (*handler).class_name(cx, proxy).as_ptr());
+    }
+    name
+}
+
+unsafe extern "C" fn fun_to_string<H: ProxyHandler>(
+    cx: *mut JSContext,
+    proxy: RawHandleObject,
+    is_to_string: bool,
+) -> *mut JSString {
+    let mut string = ptr::null_mut();
+    run_trap::<H>(cx, proxy, &mut |handler, proxy| {
+        string = handler.fun_to_string(cx, proxy, is_to_string)?;
+        Ok(())
+    });
+    string
+}
+
+unsafe extern "C" fn is_callable<H: ProxyHandler>(proxy: *mut JSObject) -> bool {
+    let handler = handler_ptr::<H>(proxy);
+    let mut result = false;
+    if !handler.is_null() {
+        wrap_panic(&mut || result = (*handler).is_callable(proxy));
+    }
+    result
+}
+
+unsafe extern "C" fn is_constructor<H: ProxyHandler>(proxy: *mut JSObject) -> bool {
+    let handler = handler_ptr::<H>(proxy);
+    let mut result = false;
+    if !handler.is_null() {
+        wrap_panic(&mut || result = (*handler).is_constructor(proxy));
+    }
+    result
+}
+
+unsafe extern "C" fn trace<H: ProxyHandler>(trc: *mut JSTracer, proxy: *mut JSObject) {
+    let handler = handler_ptr::<H>(proxy);
+    if !handler.is_null() {
+        wrap_panic(&mut || (*handler).trace(trc));
+    }
+}
+
+unsafe extern "C" fn finalize<H: ProxyHandler>(_fop: *mut JSFreeOp, proxy: *mut JSObject) {
+    let handler = handler_ptr::<H>(proxy);
+    if !handler.is_null() {
+        wrap_panic(&mut || drop(Box::from_raw(handler)));
+    }
+}
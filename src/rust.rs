@@ -9,8 +9,8 @@ use mozjs_sys::jsgc::IntoMutableHandle as IntoRawMutableHandle;
 use mozjs_sys::jsgc::RootKind;
 use mozjs_sys::{jsapi::JS::shadow::BaseShape, jsgc::CustomAutoRooterVFTable};
 
-use std::cell::Cell;
-use std::char;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::default::Default;
 use std::ffi;
 use std::ffi::CStr;
@@ -19,19 +19,24 @@ use std::mem::MaybeUninit;
 use std::ops::{Deref, DerefMut};
 use std::os::raw::c_void;
 use std::ptr;
+use std::rc::{Rc, Weak};
 use std::slice;
-use std::str;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use consts::{JSCLASS_GLOBAL_SLOT_COUNT, JSCLASS_RESERVED_SLOTS_MASK};
 use consts::{JSCLASS_IS_DOMJSCLASS, JSCLASS_IS_GLOBAL};
 
 use conversions::jsstr_to_string;
 
+use error::{ErrorReport, JSException};
+
 use jsapi;
 use jsapi::glue::{DeleteRealmOptions, JS_Init, JS_NewRealmOptions};
 use jsapi::mozilla::Utf8Unit;
+use jsapi::BigInt;
 use jsapi::Handle as RawHandle;
 use jsapi::HandleObjectVector as RawHandleObjectVector;
 use jsapi::HandleValue as RawHandleValue;
@@ -44,6 +49,11 @@ use jsapi::{BuildStackString, CaptureCurrentStack, StackFormat};
 use jsapi::{Evaluate2, HandleValueArray, Heap};
 use jsapi::{InitSelfHostedCode, IsWindowSlow};
 use jsapi::{JSAutoRealm, JS_SetGCParameter, JS_SetNativeStackQuota, JS_WrapValue};
+use jsapi::{JS_AddExtraGCRootsTracer, JS_RemoveExtraGCRootsTracer};
+use jsapi::{JS_AddInterruptCallback, JS_RequestInterruptCallback};
+use jsapi::{Call, CurrentGlobalOrNull, SetJobQueue, JS};
+use jsapi::{BuildIdCharVector, Compile1, EncodeScript, JS_ExecuteScript};
+use jsapi::{SetProcessBuildIdOp, TranscodeResult};
 use jsapi::{JSClass, JSClassOps, JSContext, Realm, JSCLASS_RESERVED_SLOTS_SHIFT};
 use jsapi::{JSErrorReport, JSFunction, JSFunctionSpec, JSGCParamKey};
 use jsapi::{JSObject, JSPropertySpec, JSRuntime, JSScript};
@@ -53,11 +63,11 @@ use jsapi::{JS_EnumerateStandardClasses, JS_GetRuntime, JS_GlobalObjectTraceHook
 use jsapi::{JS_MayResolveStandardClass, JS_NewContext, JS_ResolveStandardClass};
 use jsapi::{JS_StackCapture_AllFrames, JS_StackCapture_MaxFrames};
 use jsapi::{PersistentRootedObjectVector, ReadOnlyCompileOptions, Rooted, RootingContext};
-use jsapi::{SetWarningReporter, SourceText, Symbol, ToBooleanSlow};
+use jsapi::{SetWarningReporter, SourceText, Symbol, ToBooleanSlow, WarningReporter};
 use jsapi::{ToInt32Slow, ToInt64Slow, ToNumberSlow, ToStringSlow, ToUint16Slow};
 use jsapi::{ToUint32Slow, ToUint64Slow, ToWindowProxyIfWindowSlow};
 
-use jsval::ObjectValue;
+use jsval::{ObjectValue, UndefinedValue};
 
 use glue::{AppendToRootedObjectVector, CallFunctionTracer, CallIdTracer, CallObjectRootTracer};
 use glue::{CallObjectTracer, CallScriptTracer, CallStringTracer, CallValueRootTracer};
@@ -65,9 +75,19 @@ use glue::{CallValueTracer, CreateRootedIdVector, CreateRootedObjectVector};
 use glue::{
     DeleteCompileOptions, DeleteRootedObjectVector, DescribeScriptedCaller, DestroyRootedIdVector,
 };
+use glue::{CreateJobQueue, DeleteJobQueue, JobQueueTraps};
+use glue::{DecodeScriptFromBytes, DeleteTranscodeBuffer, GetTranscodeBufferData};
+use glue::{NewTranscodeBuffer, SetBuildId};
+use glue::{CompileOptionsParams, NewCompileOptionsWithParams, UpdateScriptDebugMetadata};
 use glue::{GetIdVectorAddress, GetObjectVectorAddress, NewCompileOptions, SliceRootedIdVector};
 
-use panic::maybe_resume_unwind;
+use panic::{maybe_resume_unwind, wrap_panic};
+
+use module::shut_down_module_loader;
+
+use promise::{shut_down_executor, wake_settled_promises};
+
+use property_key::{shut_down_interned_ids, PropertyKeys};
 
 use default_heapsize;
 
@@ -164,6 +184,13 @@ enum EngineState {
 
 lazy_static! {
     static ref ENGINE_STATE: Mutex<EngineState> = Mutex::new(EngineState::Uninitialized);
+    static ref BUILD_ID: Mutex<Vec<u8>> =
+        Mutex::new(concat!("mozjs-", env!("CARGO_PKG_VERSION")).as_bytes().to_vec());
+}
+
+unsafe extern "C" fn get_build_id(build_id: *mut BuildIdCharVector) -> bool {
+    let id = BUILD_ID.lock().unwrap();
+    SetBuildId(build_id, id.as_ptr(), id.len())
 }
 
 #[derive(Debug)]
@@ -213,6 +240,7 @@ impl JSEngine {
             Err(JSEngineError::InitFailed)
         } else {
             *state = EngineState::Initialized;
+            unsafe { SetProcessBuildIdOp(Some(get_build_id)) };
             Ok(JSEngine {
                 outstanding_handles: Arc::new(AtomicU32::new(0)),
                 marker: PhantomData,
@@ -231,6 +259,15 @@ impl JSEngine {
     }
 }
 
+/// Sets the build id embedded in encoded scripts, for every runtime of the
+/// process. Decoding bytecode encoded under a different build id fails with
+/// `TranscodeResult::Failure_BadBuildId`, so this should change whenever the
+/// embedding or SpiderMonkey is rebuilt. Defaults to the version of this
+/// crate.
+pub fn set_build_id(id: &[u8]) {
+    *BUILD_ID.lock().unwrap() = id.to_vec();
+}
+
 /// Shut down the JS engine, invalidating any existing runtimes and preventing
 /// any new ones from being created.
 impl Drop for JSEngine {
@@ -298,6 +335,12 @@ pub struct Runtime {
     /// to represent the resulting ownership graph and risk destroying a Runtime on
     /// the wrong thread.
     outstanding_children: Arc<()>,
+    /// State shared with this runtime's `InterruptHandle`s and watchdog.
+    interrupt: Arc<InterruptState>,
+    /// The built-in job queue, unless it was disabled when building.
+    job_queue: Option<RuntimeJobQueue>,
+    /// The closure installed by `set_warning_reporter`, if any.
+    warning_reporter: Rc<WarningReporterClosure>,
 }
 
 impl Runtime {
@@ -310,7 +353,9 @@ impl Runtime {
 
     /// Creates a new `JSContext`.
     pub fn new(engine: JSEngineHandle) -> Runtime {
-        unsafe { Self::create(engine, None) }
+        RuntimeBuilder::new(engine)
+            .build()
+            .expect("The default runtime configuration is valid")
     }
 
     /// Signal that a new child runtime will be created in the future, and ensure
@@ -334,43 +379,9 @@ impl Runtime {
     /// continue executing after the thread with the parent runtime panics, but they
     /// will be in an invalid and undefined state.
     pub unsafe fn create_with_parent(parent: ParentRuntime) -> Runtime {
-        Self::create(parent.engine.clone(), Some(parent))
-    }
-
-    unsafe fn create(engine: JSEngineHandle, parent: Option<ParentRuntime>) -> Runtime {
-        let parent_runtime = parent.as_ref().map_or(ptr::null_mut(), |r| r.parent);
-        let js_context = JS_NewContext(default_heapsize + (ChunkSize as u32), parent_runtime);
-        assert!(!js_context.is_null());
-
-        // Unconstrain the runtime's threshold on nominal heap size, to avoid
-        // triggering GC too often if operating continuously near an arbitrary
-        // finite threshold. This leaves the maximum-JS_malloc-bytes threshold
-        // still in effect to cause periodical, and we hope hygienic,
-        // last-ditch GCs from within the GC's allocator.
-        JS_SetGCParameter(js_context, JSGCParamKey::JSGC_MAX_BYTES, u32::MAX);
-
-        JS_SetNativeStackQuota(
-            js_context,
-            STACK_QUOTA,
-            STACK_QUOTA - SYSTEM_CODE_BUFFER,
-            STACK_QUOTA - SYSTEM_CODE_BUFFER - TRUSTED_SCRIPT_BUFFER,
-        );
-
-        CONTEXT.with(|context| {
-            assert!(context.get().is_null());
-            context.set(js_context);
-        });
-
-        InitSelfHostedCode(js_context);
-
-        SetWarningReporter(js_context, Some(report_warning));
-
-        Runtime {
-            engine,
-            _parent_child_count: parent.map(|p| p.children_of_parent),
-            cx: js_context,
-            outstanding_children: Arc::new(()),
-        }
+        RuntimeBuilder::with_parent(parent)
+            .build()
+            .expect("The default runtime configuration is valid")
     }
 
     /// Returns the `JSRuntime` object.
@@ -383,6 +394,70 @@ impl Runtime {
         self.cx
     }
 
+    /// Registers a callback that is run whenever an interrupt is serviced.
+    /// Returning `false` from any callback terminates the running script with
+    /// an uncatchable exception. All registered callbacks run on every
+    /// interrupt, in registration order.
+    pub fn add_interrupt_callback<F>(&self, callback: F)
+    where
+        F: FnMut(*mut JSContext) -> bool + 'static,
+    {
+        INTERRUPT_CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(Box::new(callback)));
+    }
+
+    /// Reports warnings to `reporter` instead of the reporter the runtime was
+    /// built with.
+    pub fn set_warning_reporter<F>(&self, reporter: F)
+    where
+        F: FnMut(*mut JSContext, &ErrorReport) + 'static,
+    {
+        *self.warning_reporter.borrow_mut() = Some(Box::new(reporter));
+        unsafe { SetWarningReporter(self.cx, Some(report_warning_to_closure)) };
+    }
+
+    /// Returns a handle that can request an interrupt of this runtime from
+    /// any thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            state: self.interrupt.clone(),
+        }
+    }
+
+    /// Limits the wall-clock time a single call to `evaluate_script` may run
+    /// for. Scripts exceeding the limit are terminated, which
+    /// `evaluate_script_with_exception`, `evaluate_script_with_options` and
+    /// `execute_script` report as a timed out `JSException`. Plain
+    /// `evaluate_script` only returns `Err(())`, like for any other
+    /// uncatchable termination. Passing `None` removes the limit.
+    pub fn set_execution_time_limit(&self, limit: Option<Duration>) {
+        let start_watchdog = {
+            let mut watchdog = self.interrupt.watchdog.lock().unwrap();
+            watchdog.limit = limit;
+            let start = limit.is_some() && !watchdog.running;
+            watchdog.running |= start;
+            start
+        };
+        if start_watchdog {
+            let state = self.interrupt.clone();
+            thread::Builder::new()
+                .name("JS watchdog".to_owned())
+                .spawn(move || state.run_watchdog())
+                .unwrap();
+            let state = self.interrupt.clone();
+            self.add_interrupt_callback(move |_| {
+                if state.deadline_passed() {
+                    TIMED_OUT.with(|timed_out| timed_out.set(true));
+                    return false;
+                }
+                true
+            });
+        }
+    }
+
+    /// Evaluates `script` in the realm of `glob`. On failure, the exception
+    /// is left pending; none is pending if the script was terminated, for
+    /// example for exceeding the execution time limit. Use
+    /// `evaluate_script_with_exception` to tell these cases apart.
     pub fn evaluate_script(
         &self,
         glob: HandleObject,
@@ -397,13 +472,15 @@ impl Runtime {
         );
 
         let _ac = JSAutoRealm::new(self.cx(), glob.get());
+        let _deadline = ExecutionDeadline::start(&self.interrupt);
         let options = unsafe { CompileOptionsWrapper::new(self.cx(), filename, line_num) };
 
         unsafe {
             let mut source = transform_str_to_source_text(&script);
-            if !Evaluate2(self.cx(), options.ptr, &mut source, rval.into()) {
+            let evaluated = Evaluate2(self.cx(), options.ptr, &mut source, rval.into());
+            maybe_resume_unwind();
+            if !evaluated {
                 debug!("...err!");
-                maybe_resume_unwind();
                 Err(())
             } else {
                 // we could return the script result but then we'd have
@@ -413,6 +490,173 @@ impl Runtime {
             }
         }
     }
+
+    /// Like `evaluate_script_with_exception`, but compiles `script` with the
+    /// given options.
+    pub fn evaluate_script_with_options(
+        &self,
+        glob: HandleObject,
+        script: &str,
+        options: &CompileOptionsBuilder,
+        rval: MutableHandleValue,
+    ) -> Result<(), JSException> {
+        let cx = self.cx();
+        let _ac = JSAutoRealm::new(cx, glob.get());
+        let _deadline = ExecutionDeadline::start(&self.interrupt);
+        unsafe {
+            let wrapper = options.build(cx);
+            let mut source = transform_str_to_source_text(script);
+            let evaluated = if options.has_debug_metadata() {
+                rooted!(in(cx) let compiled = Compile1(cx, wrapper.ptr, &mut source));
+                !compiled.is_null() &&
+                    options.update_debug_metadata(cx, compiled.handle(), &wrapper) &&
+                    JS_ExecuteScript(cx, compiled.handle().into(), rval.into())
+            } else {
+                Evaluate2(cx, wrapper.ptr, &mut source, rval.into())
+            };
+            maybe_resume_unwind();
+            if !evaluated {
+                return Err(self.take_exception());
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles `script` in the realm of `glob` without running it.
+    pub fn compile_script(
+        &self,
+        glob: HandleObject,
+        script: &str,
+        filename: &str,
+        line_num: u32,
+    ) -> Result<Script, JSException> {
+        let options = CompileOptionsBuilder::new(filename, line_num);
+        self.compile_script_with_options(glob, script, &options)
+    }
+
+    /// Compiles `script` with the given options in the realm of `glob`
+    /// without running it.
+    pub fn compile_script_with_options(
+        &self,
+        glob: HandleObject,
+        script: &str,
+        options: &CompileOptionsBuilder,
+    ) -> Result<Script, JSException> {
+        let cx = self.cx();
+        let _ac = JSAutoRealm::new(cx, glob.get());
+        unsafe {
+            let wrapper = options.build(cx);
+            let mut source = transform_str_to_source_text(script);
+            rooted!(in(cx) let compiled = Compile1(cx, wrapper.ptr, &mut source));
+            maybe_resume_unwind();
+            if compiled.is_null() ||
+                !options.update_debug_metadata(cx, compiled.handle(), &wrapper)
+            {
+                return Err(self.take_exception());
+            }
+            Ok(Script::from_raw(compiled.get()))
+        }
+    }
+
+    /// Runs a compiled script in the realm of `glob`.
+    pub fn execute_script(
+        &self,
+        glob: HandleObject,
+        script: &Script,
+        rval: MutableHandleValue,
+    ) -> Result<(), JSException> {
+        let _ac = JSAutoRealm::new(self.cx(), glob.get());
+        let _deadline = ExecutionDeadline::start(&self.interrupt);
+        unsafe {
+            let executed = JS_ExecuteScript(self.cx(), script.handle().into(), rval.into());
+            maybe_resume_unwind();
+            if !executed {
+                return Err(self.take_exception());
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `evaluate_script`, but on failure takes the pending exception
+    /// and returns it, leaving no exception pending on the context.
+    pub fn evaluate_script_with_exception(
+        &self,
+        glob: HandleObject,
+        script: &str,
+        filename: &str,
+        line_num: u32,
+        rval: MutableHandleValue,
+    ) -> Result<(), JSException> {
+        self.evaluate_script(glob, script, filename, line_num, rval)
+            .map_err(|()| {
+                let _ac = JSAutoRealm::new(self.cx(), glob.get());
+                self.take_exception()
+            })
+    }
+
+    /// Takes the pending exception after a failed operation, or describes
+    /// why none is pending. The context must be in a realm.
+    pub(crate) fn take_exception(&self) -> JSException {
+        unsafe { JSException::take(self.cx()) }.unwrap_or_else(|| {
+            if TIMED_OUT.with(|timed_out| timed_out.replace(false)) {
+                JSException::timed_out()
+            } else {
+                JSException::uncatchable()
+            }
+        })
+    }
+
+    /// Performs a microtask checkpoint: runs queued Promise jobs, including
+    /// the jobs they enqueue, until the queue is empty, then wakes the tasks
+    /// awaiting promises that have settled. Exceptions thrown by jobs are
+    /// logged and do not stop later jobs. Nested calls from within a job,
+    /// and calls on a runtime built without the built-in job queue, return
+    /// immediately.
+    pub fn run_jobs(&self) {
+        let queue = match self.job_queue {
+            Some(ref queue) => &queue.state,
+            None => return,
+        };
+        if queue.draining.get() {
+            return;
+        }
+        let _draining = Draining::new(&queue.draining);
+        while let Some(job) = queue.pop() {
+            unsafe { run_job(self.cx, job) };
+            maybe_resume_unwind();
+        }
+        wake_settled_promises();
+    }
+
+    /// Returns whether any Promise jobs are waiting for `run_jobs`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime was built without the built-in job queue.
+    pub fn has_pending_jobs(&self) -> bool {
+        !self.job_queue().jobs.borrow().is_empty()
+    }
+
+    /// Sets the hook returning the incumbent global recorded with each
+    /// enqueued job. Defaults to the global of the current realm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime was built without the built-in job queue.
+    pub fn set_incumbent_global_hook<F>(&self, hook: F)
+    where
+        F: Fn(*mut JSContext) -> *mut JSObject + 'static,
+    {
+        *self.job_queue().incumbent_global.borrow_mut() = Box::new(hook);
+    }
+
+    fn job_queue(&self) -> &JobQueueState {
+        &self
+            .job_queue
+            .as_ref()
+            .expect("This runtime was built without the built-in job queue.")
+            .state
+    }
 }
 
 impl Drop for Runtime {
@@ -422,6 +666,14 @@ impl Drop for Runtime {
             1,
             "This runtime still has live children."
         );
+        self.interrupt.shut_down();
+        shut_down_executor();
+        shut_down_module_loader();
+        shut_down_interned_ids();
+        if let Some(ref queue) = self.job_queue {
+            // Pending jobs must release their barriers while the context is alive.
+            queue.state.jobs.borrow_mut().clear();
+        }
         unsafe {
             JS_DestroyContext(self.cx);
 
@@ -430,6 +682,593 @@ impl Drop for Runtime {
                 context.set(ptr::null_mut());
             });
         }
+        INTERRUPT_CALLBACKS.with(|callbacks| callbacks.borrow_mut().clear());
+        WARNING_REPORTER.with(|reporter| *reporter.borrow_mut() = Weak::new());
+        TIMED_OUT.with(|timed_out| timed_out.set(false));
+        if let Some(queue) = self.job_queue.take() {
+            unsafe { DeleteJobQueue(queue.queue) };
+        }
+    }
+}
+
+/// Native stack quotas passed to `JS_SetNativeStackQuota`, in bytes.
+///
+/// The quota for system code is `max`; trusted and untrusted script get
+/// progressively smaller quotas by subtracting the respective buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StackQuota {
+    pub max: usize,
+    pub system_code_buffer: usize,
+    pub trusted_script_buffer: usize,
+}
+
+impl Default for StackQuota {
+    fn default() -> StackQuota {
+        StackQuota {
+            max: STACK_QUOTA,
+            system_code_buffer: SYSTEM_CODE_BUFFER,
+            trusted_script_buffer: TRUSTED_SCRIPT_BUFFER,
+        }
+    }
+}
+
+/// A builder for `Runtime` values, allowing the heap size, GC parameters,
+/// native stack quotas and warning reporter to be configured before the
+/// `JSContext` is created.
+///
+/// ```ignore
+/// let runtime = RuntimeBuilder::new(engine.handle())
+///     .heap_size(8 * 1024 * 1024)
+///     .nursery_bytes(1024 * 1024)
+///     .build()
+///     .unwrap();
+/// ```
+pub struct RuntimeBuilder {
+    engine: JSEngineHandle,
+    parent: Option<ParentRuntime>,
+    heap_size: u32,
+    gc_parameters: Vec<(JSGCParamKey, u32)>,
+    stack_quota: StackQuota,
+    warning_reporter: WarningReporter,
+    job_queue: bool,
+}
+
+impl RuntimeBuilder {
+    /// Creates a builder with the same defaults as `Runtime::new`.
+    pub fn new(engine: JSEngineHandle) -> RuntimeBuilder {
+        RuntimeBuilder {
+            engine,
+            parent: None,
+            heap_size: default_heapsize,
+            // Unconstrain the runtime's threshold on nominal heap size, to avoid
+            // triggering GC too often if operating continuously near an arbitrary
+            // finite threshold. This leaves the maximum-JS_malloc-bytes threshold
+            // still in effect to cause periodical, and we hope hygienic,
+            // last-ditch GCs from within the GC's allocator.
+            gc_parameters: vec![(JSGCParamKey::JSGC_MAX_BYTES, u32::MAX)],
+            stack_quota: StackQuota::default(),
+            warning_reporter: Some(report_warning),
+            job_queue: true,
+        }
+    }
+
+    /// Creates a builder for a runtime that will be associated with a parent
+    /// runtime. If the parent does not outlive the new runtime, its destructor
+    /// will assert.
+    ///
+    /// Unsafety:
+    /// See `Runtime::create_with_parent`.
+    pub unsafe fn with_parent(parent: ParentRuntime) -> RuntimeBuilder {
+        let mut builder = RuntimeBuilder::new(parent.engine.clone());
+        builder.parent = Some(parent);
+        builder
+    }
+
+    /// Sets the maximum number of bytes the runtime may allocate before the
+    /// nominal heap limit is reached, excluding the initial GC chunk.
+    pub fn heap_size(mut self, bytes: u32) -> RuntimeBuilder {
+        self.heap_size = bytes;
+        self
+    }
+
+    /// Sets `JSGC_MAX_BYTES`. Defaults to `u32::MAX`.
+    pub fn max_bytes(self, bytes: u32) -> RuntimeBuilder {
+        self.gc_parameter(JSGCParamKey::JSGC_MAX_BYTES, bytes)
+    }
+
+    /// Sets `JSGC_MAX_NURSERY_BYTES`.
+    pub fn nursery_bytes(self, bytes: u32) -> RuntimeBuilder {
+        self.gc_parameter(JSGCParamKey::JSGC_MAX_NURSERY_BYTES, bytes)
+    }
+
+    /// Sets an arbitrary GC parameter. Parameters are applied in the order
+    /// they were set, and setting the same key twice replaces the earlier value.
+    pub fn gc_parameter(mut self, key: JSGCParamKey, value: u32) -> RuntimeBuilder {
+        self.gc_parameters.retain(|&(k, _)| k != key);
+        self.gc_parameters.push((key, value));
+        self
+    }
+
+    /// Sets the native stack quotas. The buffers must fit within
+    /// `quota.max`, or `build` fails.
+    pub fn stack_quota(mut self, quota: StackQuota) -> RuntimeBuilder {
+        self.stack_quota = quota;
+        self
+    }
+
+    /// Sets the warning reporter. Passing `None` silences warnings; the default
+    /// logs them through `warn!`. To report warnings to a closure instead, use
+    /// `Runtime::set_warning_reporter`.
+    pub fn warning_reporter(mut self, reporter: WarningReporter) -> RuntimeBuilder {
+        self.warning_reporter = reporter;
+        self
+    }
+
+    /// Sets whether the runtime installs its built-in Promise job queue,
+    /// driven by `Runtime::run_jobs`. Defaults to `true`; disable it to
+    /// install a custom queue with `CreateJobQueue` and `SetJobQueue`.
+    pub fn job_queue(mut self, enabled: bool) -> RuntimeBuilder {
+        self.job_queue = enabled;
+        self
+    }
+
+    /// Creates the `JSContext` for this thread.
+    pub fn build(self) -> Result<Runtime, RuntimeBuildError> {
+        let max_bytes = self
+            .heap_size
+            .checked_add(ChunkSize as u32)
+            .ok_or(RuntimeBuildError::HeapSizeOverflow)?;
+        let quota = self.stack_quota;
+        let buffers = quota
+            .system_code_buffer
+            .checked_add(quota.trusted_script_buffer)
+            .ok_or(RuntimeBuildError::InvalidStackQuota)?;
+        if buffers > quota.max {
+            return Err(RuntimeBuildError::InvalidStackQuota);
+        }
+
+        unsafe {
+            let parent_runtime = self.parent.as_ref().map_or(ptr::null_mut(), |r| r.parent);
+            let js_context = JS_NewContext(max_bytes, parent_runtime);
+            assert!(!js_context.is_null());
+
+            for &(key, value) in &self.gc_parameters {
+                JS_SetGCParameter(js_context, key, value);
+            }
+
+            JS_SetNativeStackQuota(
+                js_context,
+                quota.max,
+                quota.max - quota.system_code_buffer,
+                quota.max - quota.system_code_buffer - quota.trusted_script_buffer,
+            );
+
+            CONTEXT.with(|context| {
+                assert!(context.get().is_null());
+                context.set(js_context);
+            });
+
+            InitSelfHostedCode(js_context);
+
+            SetWarningReporter(js_context, self.warning_reporter);
+
+            assert!(JS_AddInterruptCallback(js_context, Some(interrupt_callback)));
+
+            let job_queue = if self.job_queue {
+                Some(RuntimeJobQueue::install(js_context))
+            } else {
+                None
+            };
+
+            let warning_reporter = Rc::new(RefCell::new(None));
+            WARNING_REPORTER
+                .with(|current| *current.borrow_mut() = Rc::downgrade(&warning_reporter));
+
+            Ok(Runtime {
+                engine: self.engine,
+                _parent_child_count: self.parent.map(|p| p.children_of_parent),
+                cx: js_context,
+                outstanding_children: Arc::new(()),
+                interrupt: Arc::new(InterruptState {
+                    cx: Mutex::new(js_context),
+                    watchdog: Mutex::new(Watchdog::default()),
+                    wakeup: Condvar::new(),
+                }),
+                job_queue,
+                warning_reporter,
+            })
+        }
+    }
+}
+
+/// The reason a `RuntimeBuilder` could not create a runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuntimeBuildError {
+    /// The heap size plus the initial GC chunk does not fit in a `u32`.
+    HeapSizeOverflow,
+    /// The stack quota buffers do not fit within the maximum stack size.
+    InvalidStackQuota,
+}
+
+// ___________________________________________________________________________
+// Interrupts and execution time limits
+
+thread_local!(static INTERRUPT_CALLBACKS: RefCell<Vec<Box<dyn FnMut(*mut JSContext) -> bool>>> =
+    RefCell::new(Vec::new()));
+thread_local!(static TIMED_OUT: Cell<bool> = Cell::new(false));
+
+unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
+    // Callbacks may register further callbacks, so don't hold the borrow
+    // while running them.
+    let mut callbacks = INTERRUPT_CALLBACKS.with(|callbacks| callbacks.replace(Vec::new()));
+    let mut result = false;
+    wrap_panic(&mut || {
+        result = callbacks
+            .iter_mut()
+            .fold(true, |keep_going, callback| callback(cx) && keep_going);
+    });
+    INTERRUPT_CALLBACKS.with(|registered| {
+        let mut registered = registered.borrow_mut();
+        callbacks.extend(registered.drain(..));
+        *registered = callbacks;
+    });
+    result
+}
+
+/// A thread-safe handle used to interrupt a `Runtime` from another thread,
+/// for example to stop a runaway script. Obtained from
+/// `Runtime::interrupt_handle`.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    state: Arc<InterruptState>,
+}
+
+impl InterruptHandle {
+    /// Requests that the runtime run its interrupt callbacks at the next
+    /// opportunity. Returns `false` if the runtime has already been destroyed.
+    pub fn request_interrupt(&self) -> bool {
+        self.state.request_interrupt()
+    }
+}
+
+struct InterruptState {
+    /// The runtime's context, or null once it has been destroyed.
+    cx: Mutex<*mut JSContext>,
+    watchdog: Mutex<Watchdog>,
+    wakeup: Condvar,
+}
+
+// The context pointer is only used for `JS_RequestInterruptCallback`, which
+// is thread-safe, while holding the lock that `Runtime::drop` takes before
+// destroying the context.
+unsafe impl Send for InterruptState {}
+unsafe impl Sync for InterruptState {}
+
+#[derive(Default)]
+struct Watchdog {
+    limit: Option<Duration>,
+    deadline: Option<Instant>,
+    running: bool,
+    shut_down: bool,
+}
+
+impl InterruptState {
+    fn request_interrupt(&self) -> bool {
+        let cx = self.cx.lock().unwrap();
+        if cx.is_null() {
+            return false;
+        }
+        unsafe { JS_RequestInterruptCallback(*cx) };
+        true
+    }
+
+    fn deadline_passed(&self) -> bool {
+        let watchdog = self.watchdog.lock().unwrap();
+        watchdog.deadline.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    fn shut_down(&self) {
+        *self.cx.lock().unwrap() = ptr::null_mut();
+        self.watchdog.lock().unwrap().shut_down = true;
+        self.wakeup.notify_all();
+    }
+
+    fn run_watchdog(&self) {
+        let mut watchdog = self.watchdog.lock().unwrap();
+        while !watchdog.shut_down {
+            watchdog = match watchdog.deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        // Interrupt repeatedly until the deadline is cleared, in
+                        // case the request raced with another interrupt.
+                        self.request_interrupt();
+                        self.wakeup
+                            .wait_timeout(watchdog, Duration::from_millis(10))
+                            .unwrap()
+                            .0
+                    } else {
+                        self.wakeup.wait_timeout(watchdog, deadline - now).unwrap().0
+                    }
+                }
+                None => self.wakeup.wait(watchdog).unwrap(),
+            };
+        }
+    }
+}
+
+// ___________________________________________________________________________
+// Warning reporters
+
+/// The closure installed by `Runtime::set_warning_reporter`.
+type WarningReporterClosure = RefCell<Option<Box<dyn FnMut(*mut JSContext, &ErrorReport)>>>;
+
+/// The closure of the runtime on this thread, which owns it.
+thread_local!(static WARNING_REPORTER: RefCell<Weak<WarningReporterClosure>> =
+    RefCell::new(Weak::new()));
+
+unsafe extern "C" fn report_warning_to_closure(cx: *mut JSContext, report: *mut JSErrorReport) {
+    let closure = match WARNING_REPORTER.with(|current| current.borrow().upgrade()) {
+        Some(closure) => closure,
+        None => return,
+    };
+    let report = ErrorReport::from_raw(&*report);
+    // The reporter may replace itself, so don't hold the borrow while it runs.
+    let mut reporter = closure.borrow_mut().take();
+    if let Some(ref mut reporter) = reporter {
+        wrap_panic(&mut || reporter(cx, &report));
+    }
+    let mut current = closure.borrow_mut();
+    if current.is_none() {
+        *current = reporter;
+    }
+}
+
+// ___________________________________________________________________________
+// Compiled scripts
+
+/// A compiled script, rooted for as long as this value exists.
+///
+/// Scripts can be encoded to bytecode and decoded in another runtime or
+/// process, which is faster than compiling the source again. Bytecode is
+/// only accepted by builds with the same build id; see `set_build_id`.
+pub struct Script {
+    script: RootedTraceableBox<Heap<*mut JSScript>>,
+}
+
+impl Script {
+    unsafe fn from_raw(script: *mut JSScript) -> Script {
+        Script {
+            script: RootedTraceableBox::from_box(Heap::boxed(script)),
+        }
+    }
+
+    /// Returns a handle to the script.
+    pub fn handle(&self) -> Handle<*mut JSScript> {
+        self.script.handle()
+    }
+
+    /// Encodes the script to bytecode. Must be called in the realm the
+    /// script was compiled or decoded in.
+    pub fn encode(&self) -> Result<Vec<u8>, TranscodeResult> {
+        unsafe {
+            let buffer = NewTranscodeBuffer();
+            assert!(!buffer.is_null());
+            let result = EncodeScript(Runtime::get(), buffer, self.handle().into());
+            let encoded = if result == TranscodeResult::Ok {
+                let mut length = 0;
+                let data = GetTranscodeBufferData(buffer, &mut length);
+                Ok(slice::from_raw_parts(data, length).to_vec())
+            } else {
+                Err(result)
+            };
+            DeleteTranscodeBuffer(buffer);
+            encoded
+        }
+    }
+
+    /// Decodes bytecode produced by `encode` in the current realm, with the
+    /// filename and other metadata of `options`. An exception is pending if
+    /// this fails with `TranscodeResult::Throw`.
+    pub unsafe fn decode(
+        cx: *mut JSContext,
+        bytes: &[u8],
+        options: &CompileOptionsWrapper,
+    ) -> Result<Script, TranscodeResult> {
+        rooted!(in(cx) let mut script = ptr::null_mut::<JSScript>());
+        let result = DecodeScriptFromBytes(
+            cx,
+            options.ptr,
+            bytes.as_ptr(),
+            bytes.len(),
+            script.handle_mut().into(),
+        );
+        if result == TranscodeResult::Ok {
+            Ok(Script::from_raw(script.get()))
+        } else {
+            Err(result)
+        }
+    }
+}
+
+// ___________________________________________________________________________
+// Promise job queue
+
+/// The built-in `JS::JobQueue`, whose state is rooted for the lifetime of the
+/// runtime.
+struct RuntimeJobQueue {
+    queue: *mut JS::JobQueue,
+    state: RootedTraceableBox<JobQueueState>,
+}
+
+struct JobQueueState {
+    jobs: RefCell<VecDeque<QueuedJob>>,
+    incumbent_global: RefCell<Box<dyn Fn(*mut JSContext) -> *mut JSObject>>,
+    draining: Cell<bool>,
+}
+
+unsafe impl Trace for JobQueueState {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        for job in self.jobs.borrow().iter() {
+            job.job.trace(trc);
+            job.incumbent_global.trace(trc);
+        }
+    }
+}
+
+/// A Promise job, and the global that was incumbent when it was enqueued.
+struct QueuedJob {
+    job: Box<Heap<*mut JSObject>>,
+    incumbent_global: Box<Heap<*mut JSObject>>,
+}
+
+static JOB_QUEUE_TRAPS: JobQueueTraps = JobQueueTraps {
+    getIncumbentGlobal: Some(get_incumbent_global),
+    enqueuePromiseJob: Some(enqueue_promise_job),
+    empty: Some(job_queue_is_empty),
+    runJobs: Some(run_queued_jobs),
+};
+
+impl RuntimeJobQueue {
+    unsafe fn install(cx: *mut JSContext) -> RuntimeJobQueue {
+        let state = RootedTraceableBox::new(JobQueueState {
+            jobs: RefCell::new(VecDeque::new()),
+            incumbent_global: RefCell::new(Box::new(current_global)),
+            draining: Cell::new(false),
+        });
+        let queue = CreateJobQueue(
+            &JOB_QUEUE_TRAPS,
+            &*state as *const JobQueueState as *const c_void,
+        );
+        SetJobQueue(cx, queue);
+        RuntimeJobQueue { queue, state }
+    }
+}
+
+fn current_global(cx: *mut JSContext) -> *mut JSObject {
+    unsafe { CurrentGlobalOrNull(cx) }
+}
+
+impl JobQueueState {
+    fn pop(&self) -> Option<QueuedJob> {
+        self.jobs.borrow_mut().pop_front()
+    }
+}
+
+/// Resets the checkpoint flag, even when a job panics.
+struct Draining<'a>(&'a Cell<bool>);
+
+impl<'a> Draining<'a> {
+    fn new(flag: &'a Cell<bool>) -> Draining<'a> {
+        flag.set(true);
+        Draining(flag)
+    }
+}
+
+impl<'a> Drop for Draining<'a> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Runs `job` in the realm of its incumbent global, or in its own realm if
+/// there was none.
+unsafe fn run_job(cx: *mut JSContext, job: QueuedJob) {
+    rooted!(in(cx) let mut callee = ObjectValue(job.job.get()));
+    rooted!(in(cx) let incumbent_global = job.incumbent_global.get());
+    drop(job);
+    let realm = if incumbent_global.is_null() {
+        callee.to_object()
+    } else {
+        incumbent_global.get()
+    };
+    let _ac = JSAutoRealm::new(cx, realm);
+    if !JS_WrapValue(cx, callee.handle_mut().into()) {
+        warn!("Failed to wrap Promise job for its incumbent global");
+        return;
+    }
+    rooted!(in(cx) let mut rval = UndefinedValue());
+    let args = HandleValueArray::new();
+    if !Call(
+        cx,
+        HandleValue::undefined().into(),
+        callee.handle().into(),
+        &args,
+        rval.handle_mut().into(),
+    ) {
+        match JSException::take(cx) {
+            Some(exception) => warn!("Uncaught exception in Promise job: {}", exception),
+            None => warn!("Promise job was terminated"),
+        }
+    }
+}
+
+unsafe extern "C" fn get_incumbent_global(queue: *const c_void, cx: *mut JSContext) -> *mut JSObject {
+    let queue = &*(queue as *const JobQueueState);
+    let mut global = ptr::null_mut();
+    wrap_panic(&mut || global = (queue.incumbent_global.borrow())(cx));
+    global
+}
+
+unsafe extern "C" fn enqueue_promise_job(
+    queue: *const c_void,
+    _cx: *mut JSContext,
+    _promise: RawHandle<*mut JSObject>,
+    job: RawHandle<*mut JSObject>,
+    _allocation_site: RawHandle<*mut JSObject>,
+    incumbent_global: RawHandle<*mut JSObject>,
+) -> bool {
+    let queue = &*(queue as *const JobQueueState);
+    queue.jobs.borrow_mut().push_back(QueuedJob {
+        job: Heap::boxed(job.get()),
+        incumbent_global: Heap::boxed(incumbent_global.get()),
+    });
+    true
+}
+
+unsafe extern "C" fn job_queue_is_empty(queue: *const c_void) -> bool {
+    let queue = &*(queue as *const JobQueueState);
+    queue.jobs.borrow().is_empty()
+}
+
+unsafe extern "C" fn run_queued_jobs(queue: *const c_void, cx: *mut JSContext) {
+    let queue = &*(queue as *const JobQueueState);
+    if queue.draining.get() {
+        return;
+    }
+    let _draining = Draining::new(&queue.draining);
+    wrap_panic(&mut || {
+        while let Some(job) = queue.pop() {
+            run_job(cx, job);
+        }
+    });
+}
+
+/// Arms the watchdog for the duration of an evaluation, restoring the
+/// previous deadline (if any) for nested evaluations on drop.
+struct ExecutionDeadline<'a> {
+    state: &'a InterruptState,
+    previous: Option<Instant>,
+}
+
+impl<'a> ExecutionDeadline<'a> {
+    fn start(state: &'a InterruptState) -> ExecutionDeadline<'a> {
+        let mut watchdog = state.watchdog.lock().unwrap();
+        let previous = watchdog.deadline;
+        if previous.is_none() {
+            if let Some(limit) = watchdog.limit {
+                TIMED_OUT.with(|timed_out| timed_out.set(false));
+                watchdog.deadline = Some(Instant::now() + limit);
+                state.wakeup.notify_all();
+            }
+        }
+        ExecutionDeadline { state, previous }
+    }
+}
+
+impl<'a> Drop for ExecutionDeadline<'a> {
+    fn drop(&mut self) {
+        self.state.watchdog.lock().unwrap().deadline = self.previous;
     }
 }
 
@@ -484,6 +1323,121 @@ unsafe impl Trace for Heap<jsid> {
     }
 }
 
+unsafe impl<T: Trace + ?Sized> Trace for Box<T> {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        (**self).trace(trc)
+    }
+}
+
+unsafe impl<T: Trace + ?Sized> Trace for Rc<T> {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        (**self).trace(trc)
+    }
+}
+
+unsafe impl<T: Trace + ?Sized> Trace for RefCell<T> {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        // The GC may run while the cell is mutably borrowed, and its
+        // contents must be traced regardless.
+        (*self.as_ptr()).trace(trc)
+    }
+}
+
+unsafe impl<T: Trace> Trace for Option<T> {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        if let Some(ref value) = *self {
+            value.trace(trc);
+        }
+    }
+}
+
+unsafe impl<T: Trace> Trace for Vec<T> {
+    unsafe fn trace(&self, trc: *mut JSTracer) {
+        for value in self {
+            value.trace(trc);
+        }
+    }
+}
+
+/// A heap-allocated value that is traced by the GC for as long as the box is
+/// alive. The box registers an extra GC roots tracer with the runtime of the
+/// current thread, so it may outlive the stack frame that created it.
+///
+/// The runtime of the current thread must outlive the box.
+pub struct RootedTraceableBox<T: Trace + 'static> {
+    ptr: *mut T,
+}
+
+unsafe extern "C" fn trace_rooted_traceable_box<T: Trace>(trc: *mut JSTracer, data: *mut c_void) {
+    (*(data as *const T)).trace(trc);
+}
+
+impl<T: Trace + 'static> RootedTraceableBox<T> {
+    /// Moves `value` to the heap and roots it.
+    pub fn new(value: T) -> RootedTraceableBox<T> {
+        RootedTraceableBox::from_box(Box::new(value))
+    }
+
+    /// Roots an already boxed value. This must be used for `Heap` values that
+    /// were initialized after boxing, since `Heap` cannot be moved once set.
+    pub fn from_box(boxed: Box<T>) -> RootedTraceableBox<T> {
+        let ptr = Box::into_raw(boxed);
+        unsafe {
+            assert!(JS_AddExtraGCRootsTracer(
+                Runtime::get(),
+                Some(trace_rooted_traceable_box::<T>),
+                ptr as *mut c_void,
+            ));
+        }
+        RootedTraceableBox { ptr }
+    }
+}
+
+impl<T: GCMethods + Copy + 'static> RootedTraceableBox<Heap<T>>
+where
+    Heap<T>: Trace,
+{
+    /// Returns a handle to the rooted value.
+    pub fn handle(&self) -> Handle<T> {
+        unsafe { Handle::from_marked_location(self.get_unsafe()) }
+    }
+
+    /// Returns a mutable handle to the rooted value.
+    pub fn handle_mut(&self) -> MutableHandle<T> {
+        unsafe { MutableHandle::from_marked_location(self.get_unsafe()) }
+    }
+}
+
+impl<T: Trace + 'static> Deref for RootedTraceableBox<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T: Trace + 'static> DerefMut for RootedTraceableBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<T: Trace + 'static> Drop for RootedTraceableBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // If the runtime is already gone, so is its list of tracers.
+            let cx = CONTEXT.with(|context| context.get());
+            if !cx.is_null() {
+                JS_RemoveExtraGCRootsTracer(
+                    cx,
+                    Some(trace_rooted_traceable_box::<T>),
+                    self.ptr as *mut c_void,
+                );
+            }
+            drop(Box::from_raw(self.ptr));
+        }
+    }
+}
+
 /// Rust API for keeping a Rooted value in the context's root stack.
 /// Example usage: `rooted!(in(cx) let x = UndefinedValue());`.
 /// `RootedGuard::new` also works, but the macro is preferred.
@@ -750,6 +1704,7 @@ pub struct MutableHandle<'a, T: 'a> {
     anchor: PhantomData<&'a mut T>,
 }
 
+pub type HandleBigInt<'a> = Handle<'a, *mut BigInt>;
 pub type HandleFunction<'a> = Handle<'a, *mut JSFunction>;
 pub type HandleId<'a> = Handle<'a, jsid>;
 pub type HandleObject<'a> = Handle<'a, *mut JSObject>;
@@ -757,6 +1712,7 @@ pub type HandleScript<'a> = Handle<'a, *mut JSScript>;
 pub type HandleString<'a> = Handle<'a, *mut JSString>;
 pub type HandleSymbol<'a> = Handle<'a, *mut Symbol>;
 pub type HandleValue<'a> = Handle<'a, Value>;
+pub type MutableHandleBigInt<'a> = MutableHandle<'a, *mut BigInt>;
 pub type MutableHandleFunction<'a> = MutableHandle<'a, *mut JSFunction>;
 pub type MutableHandleId<'a> = MutableHandle<'a, jsid>;
 pub type MutableHandleObject<'a> = MutableHandle<'a, *mut JSObject>;
@@ -941,6 +1897,166 @@ impl Drop for CompileOptionsWrapper {
     }
 }
 
+/// A builder for `CompileOptionsWrapper` exposing the compile options that
+/// `CompileOptionsWrapper::new` leaves at their defaults.
+///
+/// The private value and element attribute name are debug metadata that
+/// SpiderMonkey attaches to a script after compiling it. They are applied by
+/// the `Runtime` APIs taking a builder, and ignored by `build`.
+///
+/// ```ignore
+/// let options = CompileOptionsBuilder::new("inline.js", 10)
+///     .column(4)
+///     .force_strict_mode(true)
+///     .no_script_rval(true);
+/// runtime.evaluate_script_with_options(global.handle(), source, &options, rval.handle_mut())?;
+/// ```
+#[derive(Clone)]
+pub struct CompileOptionsBuilder<'a> {
+    filename: String,
+    line: u32,
+    column: u32,
+    source_map_url: Option<String>,
+    introduction_type: Option<&'static CStr>,
+    force_strict_mode: bool,
+    no_script_rval: bool,
+    self_hosting_mode: bool,
+    lazy_parsing: bool,
+    private_value: Option<HandleValue<'a>>,
+    element_attribute_name: Option<HandleString<'a>>,
+}
+
+impl<'a> CompileOptionsBuilder<'a> {
+    /// Creates a builder with the same defaults as `CompileOptionsWrapper::new`.
+    pub fn new(filename: &str, line: u32) -> CompileOptionsBuilder<'a> {
+        CompileOptionsBuilder {
+            filename: filename.to_owned(),
+            line,
+            column: 0,
+            source_map_url: None,
+            introduction_type: None,
+            force_strict_mode: false,
+            no_script_rval: false,
+            self_hosting_mode: false,
+            lazy_parsing: true,
+            private_value: None,
+            element_attribute_name: None,
+        }
+    }
+
+    /// Sets the zero-based column of the first character of the source.
+    pub fn column(mut self, column: u32) -> CompileOptionsBuilder<'a> {
+        self.column = column;
+        self
+    }
+
+    /// Sets the source map URL reported to the debugger.
+    pub fn source_map_url(mut self, url: &str) -> CompileOptionsBuilder<'a> {
+        self.source_map_url = Some(url.to_owned());
+        self
+    }
+
+    /// Sets how the script was introduced, such as `eval` or `scriptElement`.
+    pub fn introduction_type(
+        mut self,
+        introduction_type: &'static CStr,
+    ) -> CompileOptionsBuilder<'a> {
+        self.introduction_type = Some(introduction_type);
+        self
+    }
+
+    /// Compiles the script as strict mode code even without a directive.
+    pub fn force_strict_mode(mut self, force: bool) -> CompileOptionsBuilder<'a> {
+        self.force_strict_mode = force;
+        self
+    }
+
+    /// Skips computing the completion value of the script.
+    pub fn no_script_rval(mut self, no_rval: bool) -> CompileOptionsBuilder<'a> {
+        self.no_script_rval = no_rval;
+        self
+    }
+
+    /// Enables self-hosting syntax, for compiling engine-internal code.
+    pub fn self_hosting_mode(mut self, self_hosting: bool) -> CompileOptionsBuilder<'a> {
+        self.self_hosting_mode = self_hosting;
+        self
+    }
+
+    /// Sets whether inner functions may be parsed lazily. Disabling this
+    /// parses the whole script up front.
+    pub fn lazy_parsing(mut self, lazy: bool) -> CompileOptionsBuilder<'a> {
+        self.lazy_parsing = lazy;
+        self
+    }
+
+    /// Sets the private value of the script's source, which the embedding
+    /// can use to find the element or module that owns it.
+    pub fn private_value(mut self, value: HandleValue<'a>) -> CompileOptionsBuilder<'a> {
+        self.private_value = Some(value);
+        self
+    }
+
+    /// Sets the name of the element attribute the source came from, for
+    /// event handler scripts.
+    pub fn element_attribute_name(mut self, name: HandleString<'a>) -> CompileOptionsBuilder<'a> {
+        self.element_attribute_name = Some(name);
+        self
+    }
+
+    /// Creates the compile options.
+    ///
+    /// If a private value or an element attribute name is set, the options
+    /// defer the debug metadata, and scripts compiled with them must be passed
+    /// to `update_debug_metadata` before they run. NUL characters in the
+    /// filename are dropped.
+    pub unsafe fn build(&self, cx: *mut JSContext) -> CompileOptionsWrapper {
+        let filename = ffi::CString::new(self.filename.replace('\0', "")).unwrap();
+        let source_map_url: Option<Vec<u16>> = self
+            .source_map_url
+            .as_ref()
+            .map(|url| url.encode_utf16().chain(Some(0)).collect());
+        let params = CompileOptionsParams {
+            filename: filename.as_ptr(),
+            line: self.line,
+            column: self.column,
+            sourceMapURL: source_map_url.as_ref().map_or(ptr::null(), |url| url.as_ptr()),
+            introductionType: self.introduction_type.map_or(ptr::null(), |t| t.as_ptr()),
+            forceStrictMode: self.force_strict_mode,
+            noScriptRval: self.no_script_rval,
+            selfHostingMode: self.self_hosting_mode,
+            forceFullParse: !self.lazy_parsing,
+            deferDebugMetadata: self.has_debug_metadata(),
+        };
+        let ptr = NewCompileOptionsWithParams(cx, &params);
+        assert!(!ptr.is_null());
+        CompileOptionsWrapper { ptr }
+    }
+
+    fn has_debug_metadata(&self) -> bool {
+        self.private_value.is_some() || self.element_attribute_name.is_some()
+    }
+
+    /// Attaches the debug metadata to a script compiled with `options`, if
+    /// there is any. `options` must come from `build` on this builder.
+    ///
+    /// Returns false with an exception pending on failure.
+    pub unsafe fn update_debug_metadata(
+        &self,
+        cx: *mut JSContext,
+        script: HandleScript,
+        options: &CompileOptionsWrapper,
+    ) -> bool {
+        if !self.has_debug_metadata() {
+            return true;
+        }
+        rooted!(in(cx) let null_name = ptr::null_mut::<JSString>());
+        let name = self.element_attribute_name.unwrap_or(null_name.handle());
+        let private = self.private_value.unwrap_or(HandleValue::undefined());
+        UpdateScriptDebugMetadata(cx, script.into(), options.ptr, private.into(), name.into())
+    }
+}
+
 // ___________________________________________________________________________
 // Fast inline converters
 
@@ -1052,6 +2168,7 @@ pub unsafe fn ToWindowProxyIfWindow(obj: *mut JSObject) -> *mut JSObject {
     }
 }
 
+/// The default warning reporter, which logs warnings through `warn!`.
 pub unsafe extern "C" fn report_warning(_cx: *mut JSContext, report: *mut JSErrorReport) {
     fn latin1_to_string(bytes: &[u8]) -> String {
         bytes
@@ -1068,17 +2185,11 @@ pub unsafe extern "C" fn report_warning(_cx: *mut JSContext, report: *mut JSErro
         "none".to_string()
     };
 
-    let lineno = (*report)._base.lineno;
-    let column = (*report)._base.column;
-
-    let msg_ptr = (*report)._base.message_.data_ as *const u8;
-    let msg_len = (0usize..)
-        .find(|&i| *msg_ptr.offset(i as isize) == 0)
-        .unwrap();
-    let msg_slice = slice::from_raw_parts(msg_ptr, msg_len);
-    let msg = str::from_utf8_unchecked(msg_slice);
-
-    warn!("Warning at {}:{}:{}: {}\n", fname, lineno, column, msg);
+    let report = ErrorReport::from_raw(&*report);
+    warn!(
+        "Warning at {}:{}:{}: {}\n",
+        fname, report.line, report.column, report.message
+    );
 }
 
 pub struct IdVector(*mut PersistentRootedIdVector);
@@ -1095,6 +2206,11 @@ impl IdVector {
             ptr: unsafe { GetIdVectorAddress(self.0) },
         }
     }
+
+    /// Returns an iterator over the ids as `PropertyKey`s.
+    pub unsafe fn keys(&self, cx: *mut JSContext) -> PropertyKeys {
+        PropertyKeys::new(cx, self)
+    }
 }
 
 impl Drop for IdVector {
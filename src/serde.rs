@@ -0,0 +1,1061 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Conversions of serde data structures to and from `JSVal`.
+//!
+//! | serde type                 | JS type                                     |
+//! |----------------------------|---------------------------------------------|
+//! | bool                       | boolean                                     |
+//! | integers                   | number, or BigInt beyond 2^53 - 1           |
+//! | floats                     | number                                      |
+//! | char, string               | string                                      |
+//! | bytes                      | `Uint8Array`                                |
+//! | option                     | `null` or the value                         |
+//! | unit, unit struct          | `undefined`                                 |
+//! | seq, tuple, tuple struct   | array                                       |
+//! | map, struct                | plain object                                |
+//! | unit variant               | string                                      |
+//! | other variants             | object with a single member for the variant |
+//!
+//! When converting from JS, integers are read from integral numbers and from
+//! BigInts, bytes from `Uint8Array`s, `ArrayBuffer`s and arrays, and maps
+//! from the own enumerable properties of any object. Errors carry the path
+//! of the member that failed to convert.
+
+use conversions::{jsstr_to_string, ToJSValConvertible};
+use error::JSException;
+use jsapi::{BigIntFromInt64, BigIntFromUint64, BigIntIsInt64, BigIntIsUint64};
+use jsapi::{GetArrayLength, GetPropertyKeys, IsArrayObject1, JSContext, JSObject};
+use jsapi::{Heap, JS_DefineElement, JS_DefineUCProperty2, JS_GetElement, JS_GetPropertyById};
+use jsapi::{JS_IdToValue, JS_NewPlainObject, NewArrayObject1, JSITER_OWNONLY, JSPROP_ENUMERATE};
+use jsval::{BigIntValue, BooleanValue, NullValue, ObjectValue, UndefinedValue};
+use rust::ToString;
+use rust::{HandleObject, HandleValue, IdVector, MutableHandleValue, RootedTraceableBox};
+use serde_crate::de::value::{SeqDeserializer, StringDeserializer};
+use serde_crate::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer};
+use serde_crate::de::{EnumAccess, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor};
+use serde_crate::ser::{self, Serialize, Serializer};
+use typedarray::{ArrayBuffer, CreateWith, Uint8Array};
+
+use std::fmt;
+use std::ptr;
+
+/// The largest integer that a number represents exactly.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Converts `value` to a JS value in the current realm of `cx`.
+pub unsafe fn to_jsval<T: ?Sized + Serialize>(
+    cx: *mut JSContext,
+    value: &T,
+    rval: MutableHandleValue,
+) -> Result<(), Error> {
+    value.serialize(ValueSerializer { cx, rval })
+}
+
+/// Converts the JS value `value` to a `T`.
+pub unsafe fn from_jsval<T: DeserializeOwned>(
+    cx: *mut JSContext,
+    value: HandleValue,
+) -> Result<T, Error> {
+    T::deserialize(ValueDeserializer { cx, value })
+}
+
+// ___________________________________________________________________________
+// Errors
+
+/// A step from a value to one of its members.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// An element of an array.
+    Index(usize),
+    /// A member of an object.
+    Member(String),
+}
+
+/// A failed conversion, and where in the converted value it happened.
+#[derive(Clone, Debug)]
+pub struct Error {
+    message: String,
+    path: Vec<PathSegment>,
+}
+
+impl Error {
+    fn new<T: Into<String>>(message: T) -> Error {
+        Error {
+            message: message.into(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Takes the pending exception of a failed JSAPI call.
+    unsafe fn pending(cx: *mut JSContext) -> Error {
+        match JSException::take(cx) {
+            Some(exception) => Error::new(exception.to_string()),
+            None => Error::new("uncatchable exception"),
+        }
+    }
+
+    /// Records that the error happened below `segment`.
+    fn at(mut self, segment: PathSegment) -> Error {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// Returns the description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the path from the converted value to the member that failed to
+    /// convert, outermost first.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.path.iter().enumerate() {
+            match *segment {
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+                PathSegment::Member(ref name) if i == 0 => write!(f, "{}", name)?,
+                PathSegment::Member(ref name) => write!(f, ".{}", name)?,
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ::std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        Error::new(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        Error::new(message.to_string())
+    }
+}
+
+// ___________________________________________________________________________
+// Serialization
+
+unsafe fn new_object(cx: *mut JSContext) -> Result<RootedTraceableBox<Heap<*mut JSObject>>, Error> {
+    let object = JS_NewPlainObject(cx);
+    if object.is_null() {
+        return Err(Error::pending(cx));
+    }
+    Ok(RootedTraceableBox::from_box(Heap::boxed(object)))
+}
+
+unsafe fn define_member(
+    cx: *mut JSContext,
+    object: HandleObject,
+    name: &str,
+    value: HandleValue,
+) -> Result<(), Error> {
+    let name: Vec<u16> = name.encode_utf16().collect();
+    if JS_DefineUCProperty2(
+        cx,
+        object.into(),
+        name.as_ptr(),
+        name.len(),
+        value.into(),
+        JSPROP_ENUMERATE as u32,
+    ) {
+        Ok(())
+    } else {
+        Err(Error::pending(cx))
+    }
+}
+
+/// Stores `value`, wrapped in an object with a member named `variant` if
+/// there is one, in `rval`.
+unsafe fn finish(
+    cx: *mut JSContext,
+    variant: Option<&'static str>,
+    value: HandleValue,
+    mut rval: MutableHandleValue,
+) -> Result<(), Error> {
+    match variant {
+        Some(variant) => {
+            let object = new_object(cx)?;
+            define_member(cx, object.handle(), variant, value)?;
+            rval.set(ObjectValue(object.get()));
+        }
+        None => rval.set(value.get()),
+    }
+    Ok(())
+}
+
+struct ValueSerializer<'a> {
+    cx: *mut JSContext,
+    rval: MutableHandleValue<'a>,
+}
+
+impl<'a> ValueSerializer<'a> {
+    fn serialize_primitive<T: ToJSValConvertible + ?Sized>(self, value: &T) -> Result<(), Error> {
+        unsafe { value.to_jsval(self.cx, self.rval) };
+        Ok(())
+    }
+}
+
+impl<'a> Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ArraySerializer<'a>;
+    type SerializeTuple = ArraySerializer<'a>;
+    type SerializeTupleStruct = ArraySerializer<'a>;
+    type SerializeTupleVariant = ArraySerializer<'a>;
+    type SerializeMap = ObjectSerializer<'a>;
+    type SerializeStruct = ObjectSerializer<'a>;
+    type SerializeStructVariant = ObjectSerializer<'a>;
+
+    fn serialize_bool(mut self, value: bool) -> Result<(), Error> {
+        self.rval.set(BooleanValue(value));
+        Ok(())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_i64(mut self, value: i64) -> Result<(), Error> {
+        if value.unsigned_abs() <= MAX_SAFE_INTEGER {
+            return self.serialize_primitive(&(value as f64));
+        }
+        unsafe {
+            let bigint = BigIntFromInt64(self.cx, value);
+            if bigint.is_null() {
+                return Err(Error::pending(self.cx));
+            }
+            self.rval.set(BigIntValue(&*bigint));
+        }
+        Ok(())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<(), Error> {
+        if value >= i64::MIN as i128 && value <= i64::MAX as i128 {
+            self.serialize_i64(value as i64)
+        } else if value >= 0 && value <= u64::MAX as i128 {
+            self.serialize_u64(value as u64)
+        } else {
+            Err(Error::new(format!(
+                "{} is out of the range of BigInts",
+                value
+            )))
+        }
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_u64(mut self, value: u64) -> Result<(), Error> {
+        if value <= MAX_SAFE_INTEGER {
+            return self.serialize_primitive(&(value as f64));
+        }
+        unsafe {
+            let bigint = BigIntFromUint64(self.cx, value);
+            if bigint.is_null() {
+                return Err(Error::pending(self.cx));
+            }
+            self.rval.set(BigIntValue(&*bigint));
+        }
+        Ok(())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<(), Error> {
+        if value <= u64::MAX as u128 {
+            self.serialize_u64(value as u64)
+        } else {
+            Err(Error::new(format!(
+                "{} is out of the range of BigInts",
+                value
+            )))
+        }
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), Error> {
+        self.serialize_primitive(&value)
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.serialize_primitive(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.serialize_primitive(value)
+    }
+
+    fn serialize_bytes(mut self, value: &[u8]) -> Result<(), Error> {
+        unsafe {
+            rooted!(in(self.cx) let mut array = ptr::null_mut::<JSObject>());
+            if Uint8Array::create(self.cx, CreateWith::Slice(value), array.handle_mut()).is_err() {
+                return Err(Error::pending(self.cx));
+            }
+            self.rval.set(ObjectValue(array.get()));
+        }
+        Ok(())
+    }
+
+    fn serialize_none(mut self) -> Result<(), Error> {
+        self.rval.set(NullValue());
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(mut self) -> Result<(), Error> {
+        self.rval.set(UndefinedValue());
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        unsafe {
+            rooted!(in(self.cx) let mut inner = UndefinedValue());
+            value
+                .serialize(ValueSerializer {
+                    cx: self.cx,
+                    rval: inner.handle_mut(),
+                })
+                .map_err(|error| error.at(PathSegment::Member(variant.to_owned())))?;
+            finish(self.cx, Some(variant), inner.handle(), self.rval)
+        }
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArraySerializer<'a>, Error> {
+        unsafe { ArraySerializer::new(self, len.unwrap_or(0), None) }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer<'a>, Error> {
+        unsafe { ArraySerializer::new(self, len, None) }
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer<'a>, Error> {
+        unsafe { ArraySerializer::new(self, len, None) }
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer<'a>, Error> {
+        unsafe { ArraySerializer::new(self, len, Some(variant)) }
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ObjectSerializer<'a>, Error> {
+        unsafe { ObjectSerializer::new(self, None) }
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<ObjectSerializer<'a>, Error> {
+        unsafe { ObjectSerializer::new(self, None) }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ObjectSerializer<'a>, Error> {
+        unsafe { ObjectSerializer::new(self, Some(variant)) }
+    }
+}
+
+struct ArraySerializer<'a> {
+    cx: *mut JSContext,
+    array: RootedTraceableBox<Heap<*mut JSObject>>,
+    index: u32,
+    variant: Option<&'static str>,
+    rval: MutableHandleValue<'a>,
+}
+
+impl<'a> ArraySerializer<'a> {
+    unsafe fn new(
+        serializer: ValueSerializer<'a>,
+        len: usize,
+        variant: Option<&'static str>,
+    ) -> Result<ArraySerializer<'a>, Error> {
+        let array = NewArrayObject1(serializer.cx, len);
+        if array.is_null() {
+            return Err(Error::pending(serializer.cx));
+        }
+        Ok(ArraySerializer {
+            cx: serializer.cx,
+            array: RootedTraceableBox::from_box(Heap::boxed(array)),
+            index: 0,
+            variant,
+            rval: serializer.rval,
+        })
+    }
+
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let index = self.index;
+        unsafe {
+            rooted!(in(self.cx) let mut element = UndefinedValue());
+            value
+                .serialize(ValueSerializer {
+                    cx: self.cx,
+                    rval: element.handle_mut(),
+                })
+                .map_err(|error| error.at(PathSegment::Index(index as usize)))?;
+            if !JS_DefineElement(
+                self.cx,
+                self.array.handle().into(),
+                index,
+                element.handle().into(),
+                JSPROP_ENUMERATE as u32,
+            ) {
+                return Err(Error::pending(self.cx));
+            }
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn complete(self) -> Result<(), Error> {
+        unsafe {
+            rooted!(in(self.cx) let array = ObjectValue(self.array.get()));
+            finish(self.cx, self.variant, array.handle(), self.rval)
+        }
+    }
+}
+
+impl<'a> ser::SerializeSeq for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+impl<'a> ser::SerializeTuple for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for ArraySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+struct ObjectSerializer<'a> {
+    cx: *mut JSContext,
+    object: RootedTraceableBox<Heap<*mut JSObject>>,
+    key: Option<String>,
+    variant: Option<&'static str>,
+    rval: MutableHandleValue<'a>,
+}
+
+impl<'a> ObjectSerializer<'a> {
+    unsafe fn new(
+        serializer: ValueSerializer<'a>,
+        variant: Option<&'static str>,
+    ) -> Result<ObjectSerializer<'a>, Error> {
+        Ok(ObjectSerializer {
+            cx: serializer.cx,
+            object: new_object(serializer.cx)?,
+            key: None,
+            variant,
+            rval: serializer.rval,
+        })
+    }
+
+    fn insert<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        unsafe {
+            rooted!(in(self.cx) let mut member = UndefinedValue());
+            value
+                .serialize(ValueSerializer {
+                    cx: self.cx,
+                    rval: member.handle_mut(),
+                })
+                .map_err(|error| error.at(PathSegment::Member(key.to_owned())))?;
+            define_member(self.cx, self.object.handle(), key, member.handle())
+        }
+    }
+
+    fn complete(self) -> Result<(), Error> {
+        unsafe {
+            rooted!(in(self.cx) let object = ObjectValue(self.object.get()));
+            finish(self.cx, self.variant, object.handle(), self.rval)
+        }
+    }
+}
+
+impl<'a> ser::SerializeMap for ObjectSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        // Keys are converted like values, and then to property names.
+        unsafe {
+            rooted!(in(self.cx) let mut value = UndefinedValue());
+            key.serialize(ValueSerializer {
+                cx: self.cx,
+                rval: value.handle_mut(),
+            })?;
+            if !value.is_string() && !value.is_number() {
+                return Err(Error::new("map keys must be strings or numbers"));
+            }
+            rooted!(in(self.cx) let string = ToString(self.cx, value.handle()));
+            if string.is_null() {
+                return Err(Error::pending(self.cx));
+            }
+            self.key = Some(jsstr_to_string(self.cx, string.get()));
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+impl<'a> ser::SerializeStruct for ObjectSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for ObjectSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.complete()
+    }
+}
+
+// ___________________________________________________________________________
+// Deserialization
+
+struct ValueDeserializer<'a> {
+    cx: *mut JSContext,
+    value: HandleValue<'a>,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    unsafe fn string(&self) -> String {
+        jsstr_to_string(self.cx, self.value.to_string())
+    }
+
+    /// Returns the bytes of a `Uint8Array` or `ArrayBuffer` value.
+    fn bytes(&self) -> Option<Vec<u8>> {
+        if !self.value.is_object() {
+            return None;
+        }
+        let object = self.value.to_object();
+        if let Ok(array) = Uint8Array::from(object) {
+            return Some(array.to_vec());
+        }
+        ArrayBuffer::from(object).ok().map(|buffer| buffer.to_vec())
+    }
+
+    fn deserialize_integer<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.value;
+        if value.is_int32() {
+            return visitor.visit_i32(value.to_int32());
+        }
+        if value.is_double() {
+            let number = value.to_double();
+            if number.fract() != 0.0 || !number.is_finite() {
+                return Err(de::Error::invalid_type(Unexpected::Float(number), &visitor));
+            }
+            if number >= 0.0 && number < u64::MAX as f64 {
+                return visitor.visit_u64(number as u64);
+            }
+            if number >= i64::MIN as f64 {
+                return visitor.visit_i64(number as i64);
+            }
+            return Err(de::Error::invalid_value(
+                Unexpected::Float(number),
+                &visitor,
+            ));
+        }
+        self.deserialize_any(visitor)
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.value;
+        if value.is_null_or_undefined() {
+            visitor.visit_unit()
+        } else if value.is_boolean() {
+            visitor.visit_bool(value.to_boolean())
+        } else if value.is_int32() {
+            visitor.visit_i32(value.to_int32())
+        } else if value.is_double() {
+            visitor.visit_f64(value.to_double())
+        } else if value.is_bigint() {
+            let bigint = value.to_bigint();
+            let (mut signed, mut unsigned) = (0, 0);
+            unsafe {
+                if BigIntIsInt64(bigint, &mut signed) {
+                    visitor.visit_i64(signed)
+                } else if BigIntIsUint64(bigint, &mut unsigned) {
+                    visitor.visit_u64(unsigned)
+                } else {
+                    Err(Error::new("BigInt is out of the range of 64-bit integers"))
+                }
+            }
+        } else if value.is_string() {
+            visitor.visit_string(unsafe { self.string() })
+        } else if value.is_object() {
+            if let Some(bytes) = self.bytes() {
+                let mut seq: SeqDeserializer<_, Error> = SeqDeserializer::new(bytes.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                return Ok(value);
+            }
+            unsafe {
+                rooted!(in(self.cx) let object = value.to_object());
+                let mut is_array = false;
+                if !IsArrayObject1(self.cx, object.handle().into(), &mut is_array) {
+                    return Err(Error::pending(self.cx));
+                }
+                if is_array {
+                    let mut length = 0;
+                    if !GetArrayLength(self.cx, object.handle().into(), &mut length) {
+                        return Err(Error::pending(self.cx));
+                    }
+                    visitor.visit_seq(ArrayAccess {
+                        cx: self.cx,
+                        array: object.handle(),
+                        index: 0,
+                        length,
+                    })
+                } else {
+                    visitor.visit_map(ObjectAccess::new(self.cx, object.handle())?)
+                }
+            }
+        } else {
+            Err(Error::new("symbols cannot be deserialized"))
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_integer(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.bytes() {
+            Some(bytes) => visitor.visit_byte_buf(bytes),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.is_null_or_undefined() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.value.is_string() {
+            let variant: StringDeserializer<Error> = unsafe { self.string() }.into_deserializer();
+            return visitor.visit_enum(variant);
+        }
+        if !self.value.is_object() {
+            return self.deserialize_any(visitor);
+        }
+        unsafe {
+            rooted!(in(self.cx) let object = self.value.to_object());
+            let mut members = ObjectAccess::new(self.cx, object.handle())?;
+            let variant = match members.next_key::<String>()? {
+                Some(variant) => variant,
+                None => return Err(Error::new("expected an object with a single member")),
+            };
+            rooted!(in(self.cx) let mut inner = UndefinedValue());
+            members.get(inner.handle_mut())?;
+            if members.next_key::<String>()?.is_some() {
+                return Err(Error::new("expected an object with a single member"));
+            }
+            visitor.visit_enum(Enum {
+                cx: self.cx,
+                variant,
+                value: inner.handle(),
+            })
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string unit unit_struct seq tuple tuple_struct map struct
+        identifier
+    }
+}
+
+struct ArrayAccess<'a> {
+    cx: *mut JSContext,
+    array: HandleObject<'a>,
+    index: u32,
+    length: u32,
+}
+
+impl<'de, 'a> SeqAccess<'de> for ArrayAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.index >= self.length {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        unsafe {
+            rooted!(in(self.cx) let mut element = UndefinedValue());
+            if !JS_GetElement(
+                self.cx,
+                self.array.into(),
+                index,
+                element.handle_mut().into(),
+            ) {
+                return Err(Error::pending(self.cx).at(PathSegment::Index(index as usize)));
+            }
+            seed.deserialize(ValueDeserializer {
+                cx: self.cx,
+                value: element.handle(),
+            })
+            .map(Some)
+            .map_err(|error| error.at(PathSegment::Index(index as usize)))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.length - self.index) as usize)
+    }
+}
+
+/// The own enumerable properties of an object.
+struct ObjectAccess<'a> {
+    cx: *mut JSContext,
+    object: HandleObject<'a>,
+    ids: IdVector,
+    index: usize,
+    key: String,
+}
+
+impl<'a> ObjectAccess<'a> {
+    unsafe fn new(cx: *mut JSContext, object: HandleObject<'a>) -> Result<ObjectAccess<'a>, Error> {
+        let mut ids = IdVector::new(cx);
+        if !GetPropertyKeys(cx, object.into(), JSITER_OWNONLY, ids.handle_mut()) {
+            return Err(Error::pending(cx));
+        }
+        Ok(ObjectAccess {
+            cx,
+            object,
+            ids,
+            index: 0,
+            key: String::new(),
+        })
+    }
+
+    /// Gets the value of the last key returned by `next_key_seed`.
+    unsafe fn get(&self, value: MutableHandleValue) -> Result<(), Error> {
+        rooted!(in(self.cx) let id = self.ids[self.index - 1]);
+        if JS_GetPropertyById(
+            self.cx,
+            self.object.into(),
+            id.handle().into(),
+            value.into(),
+        ) {
+            Ok(())
+        } else {
+            Err(Error::pending(self.cx).at(PathSegment::Member(self.key.clone())))
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for ObjectAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.index >= self.ids.len() {
+            return Ok(None);
+        }
+        unsafe {
+            rooted!(in(self.cx) let mut key = UndefinedValue());
+            if !JS_IdToValue(self.cx, self.ids[self.index], key.handle_mut().into()) {
+                return Err(Error::pending(self.cx));
+            }
+            self.index += 1;
+            // Index keys are numbers.
+            rooted!(in(self.cx) let string = ToString(self.cx, key.handle()));
+            if string.is_null() {
+                return Err(Error::pending(self.cx));
+            }
+            self.key = jsstr_to_string(self.cx, string.get());
+        }
+        let key: StringDeserializer<Error> = self.key.clone().into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        unsafe {
+            rooted!(in(self.cx) let mut value = UndefinedValue());
+            self.get(value.handle_mut())?;
+            seed.deserialize(ValueDeserializer {
+                cx: self.cx,
+                value: value.handle(),
+            })
+            .map_err(|error| error.at(PathSegment::Member(self.key.clone())))
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.ids.len() - self.index)
+    }
+}
+
+/// A variant represented by an object with a single member.
+struct Enum<'a> {
+    cx: *mut JSContext,
+    variant: String,
+    value: HandleValue<'a>,
+}
+
+impl<'a> Enum<'a> {
+    fn value(&self) -> ValueDeserializer<'a> {
+        ValueDeserializer {
+            cx: self.cx,
+            value: self.value,
+        }
+    }
+
+    fn member(&self) -> PathSegment {
+        PathSegment::Member(self.variant.clone())
+    }
+}
+
+impl<'de, 'a> EnumAccess<'de> for Enum<'a> {
+    type Error = Error;
+    type Variant = Enum<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Enum<'a>), Error> {
+        let variant: StringDeserializer<Error> = self.variant.clone().into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Enum<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.value())
+            .map_err(|error| error.at(self.member()))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.value()
+            .deserialize_seq(visitor)
+            .map_err(|error| error.at(self.member()))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value()
+            .deserialize_map(visitor)
+            .map_err(|error| error.at(self.member()))
+    }
+}
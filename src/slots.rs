@@ -0,0 +1,315 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Typed access to the reserved slots of objects.
+//!
+//! A `SlotLayout` describes the reserved slots of a class, and hands out a
+//! `Slot` for each of them that reads and writes one Rust type, after
+//! checking that the object is an instance of the class. Raw pointers are
+//! stored through a `PrivateSlot` instead.
+//!
+//! ```ignore
+//! static POINT_CLASS: JSClass = JSClass {
+//!     name: b"Point\0" as *const u8 as *const libc::c_char,
+//!     flags: reserved_slots_flags(2),
+//!     ..
+//! };
+//!
+//! let layout = SlotLayout::new(&POINT_CLASS, 2);
+//! let x: Slot<f64> = layout.slot(0);
+//! let label: Slot<*mut JSString> = layout.slot(1);
+//!
+//! x.set(point.get(), 1.5)?;
+//! assert_eq!(x.get(point.get()), Some(1.5));
+//! ```
+//!
+//! Slots are part of the object, so the GC things stored in them are traced
+//! along with it and need no further rooting.
+
+use consts::JSCLASS_RESERVED_SLOTS_MASK;
+use glue::JS_GetReservedSlot;
+use jsapi::JSCLASS_RESERVED_SLOTS_SHIFT;
+use jsapi::{JSClass, JSObject, JSString, JS_SetReservedSlot, Value};
+use jsval::UndefinedValue;
+use jsval::{BooleanValue, DoubleValue, ObjectOrNullValue, PrivateValue, StringValue};
+use rust::get_object_class;
+
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+/// A Rust type that can be stored in a reserved slot.
+///
+/// Slots don't record which Rust type they were written with, so a value is
+/// only read back as the type it was stored as if the layout is followed.
+pub trait SlotType: Sized {
+    /// Converts `self` to the value stored in the slot.
+    unsafe fn to_slot(self) -> Value;
+
+    /// Converts the value stored in a slot, or returns `None` if it holds a
+    /// value of another type, such as `undefined` before it is first set.
+    unsafe fn from_slot(value: Value) -> Option<Self>;
+}
+
+impl SlotType for f64 {
+    unsafe fn to_slot(self) -> Value {
+        DoubleValue(self)
+    }
+
+    unsafe fn from_slot(value: Value) -> Option<f64> {
+        if value.is_number() {
+            Some(value.to_number())
+        } else {
+            None
+        }
+    }
+}
+
+impl SlotType for bool {
+    unsafe fn to_slot(self) -> Value {
+        BooleanValue(self)
+    }
+
+    unsafe fn from_slot(value: Value) -> Option<bool> {
+        if value.is_boolean() {
+            Some(value.to_boolean())
+        } else {
+            None
+        }
+    }
+}
+
+/// Objects, or null.
+impl SlotType for *mut JSObject {
+    unsafe fn to_slot(self) -> Value {
+        ObjectOrNullValue(self)
+    }
+
+    unsafe fn from_slot(value: Value) -> Option<*mut JSObject> {
+        if value.is_object_or_null() {
+            Some(value.to_object_or_null())
+        } else {
+            None
+        }
+    }
+}
+
+/// Strings, which must not be null.
+impl SlotType for *mut JSString {
+    unsafe fn to_slot(self) -> Value {
+        assert!(!self.is_null());
+        StringValue(&*self)
+    }
+
+    unsafe fn from_slot(value: Value) -> Option<*mut JSString> {
+        if value.is_string() {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the `JSClass` flags reserving `count` slots. Using this to
+/// define a class fails to compile if `count` exceeds
+/// `JSCLASS_RESERVED_SLOTS_MASK`.
+pub const fn reserved_slots_flags(count: u32) -> u32 {
+    assert!(
+        count <= JSCLASS_RESERVED_SLOTS_MASK,
+        "A class cannot reserve that many slots"
+    );
+    count << JSCLASS_RESERVED_SLOTS_SHIFT
+}
+
+/// The reserved slots of a class.
+#[derive(Clone, Copy)]
+pub struct SlotLayout {
+    class: &'static JSClass,
+    count: u32,
+}
+
+impl SlotLayout {
+    /// Describes the first `count` reserved slots of `class`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `class` reserves fewer than `count` slots.
+    pub fn new(class: &'static JSClass, count: u32) -> SlotLayout {
+        let reserved = (class.flags >> JSCLASS_RESERVED_SLOTS_SHIFT) & JSCLASS_RESERVED_SLOTS_MASK;
+        assert!(
+            reserved >= count,
+            "The class reserves {} slots, but {} are used",
+            reserved,
+            count
+        );
+        SlotLayout { class, count }
+    }
+
+    /// Returns the class the slots belong to.
+    pub fn class(&self) -> &'static JSClass {
+        self.class
+    }
+
+    /// Returns the number of slots.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the slot at `index`, holding a `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the number of slots.
+    pub fn slot<T: SlotType>(&self, index: u32) -> Slot<T> {
+        self.check_index(index);
+        Slot {
+            class: self.class,
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the slot at `index`, holding a raw pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the number of slots.
+    pub fn private_slot(&self, index: u32) -> PrivateSlot {
+        self.check_index(index);
+        PrivateSlot {
+            class: self.class,
+            index,
+        }
+    }
+
+    fn check_index(&self, index: u32) {
+        assert!(
+            index < self.count,
+            "Slot {} is out of range for a layout of {} slots",
+            index,
+            self.count
+        );
+    }
+
+    /// Returns whether `obj` is an instance of the class.
+    pub unsafe fn is_instance(&self, obj: *mut JSObject) -> bool {
+        is_instance(obj, self.class)
+    }
+}
+
+unsafe fn is_instance(obj: *mut JSObject, class: &'static JSClass) -> bool {
+    get_object_class(obj) == class as *const JSClass
+}
+
+/// A reserved slot holding a `T`, returned by `SlotLayout::slot`.
+pub struct Slot<T> {
+    class: &'static JSClass,
+    index: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Slot<T> {
+    fn clone(&self) -> Slot<T> {
+        *self
+    }
+}
+
+impl<T> Copy for Slot<T> {}
+
+impl<T: SlotType> Slot<T> {
+    /// Returns the index of the slot.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Reads the slot of `obj`. Returns `None` if `obj` is not an instance
+    /// of the class, or if the slot holds a value of another type.
+    ///
+    /// Cross-compartment wrappers are not unwrapped, so they are never
+    /// considered instances.
+    pub unsafe fn get(&self, obj: *mut JSObject) -> Option<T> {
+        if !is_instance(obj, self.class) {
+            return None;
+        }
+        let mut value = UndefinedValue();
+        JS_GetReservedSlot(obj, self.index, &mut value);
+        T::from_slot(value)
+    }
+
+    /// Stores `value` in the slot of `obj`. Fails if `obj` is not an
+    /// instance of the class.
+    pub unsafe fn set(&self, obj: *mut JSObject, value: T) -> Result<(), ()> {
+        if !is_instance(obj, self.class) {
+            return Err(());
+        }
+        JS_SetReservedSlot(obj, self.index, &value.to_slot());
+        Ok(())
+    }
+
+    /// Resets the slot of `obj` to `undefined`. Fails if `obj` is not an
+    /// instance of the class.
+    pub unsafe fn clear(&self, obj: *mut JSObject) -> Result<(), ()> {
+        if !is_instance(obj, self.class) {
+            return Err(());
+        }
+        JS_SetReservedSlot(obj, self.index, &UndefinedValue());
+        Ok(())
+    }
+}
+
+/// A reserved slot holding a raw pointer, returned by
+/// `SlotLayout::private_slot`. The pointer is stored as a `PrivateValue`,
+/// which the GC ignores.
+#[derive(Clone, Copy)]
+pub struct PrivateSlot {
+    class: &'static JSClass,
+    index: u32,
+}
+
+impl PrivateSlot {
+    /// Returns the index of the slot.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Reads the pointer in the slot of `obj`. Returns `None` if `obj` is
+    /// not an instance of the class, or if the slot is not set.
+    ///
+    /// # Safety
+    ///
+    /// Private values are doubles, so the slot of `obj` must only ever be
+    /// written through a `PrivateSlot`: a number stored by a `Slot<f64>`
+    /// would be read back as a dangling pointer.
+    pub unsafe fn get(&self, obj: *mut JSObject) -> Option<*const c_void> {
+        if !is_instance(obj, self.class) {
+            return None;
+        }
+        let mut value = UndefinedValue();
+        JS_GetReservedSlot(obj, self.index, &mut value);
+        if value.is_double() {
+            Some(value.to_private())
+        } else {
+            None
+        }
+    }
+
+    /// Stores `pointer` in the slot of `obj`. Fails if `obj` is not an
+    /// instance of the class.
+    pub unsafe fn set(&self, obj: *mut JSObject, pointer: *const c_void) -> Result<(), ()> {
+        if !is_instance(obj, self.class) {
+            return Err(());
+        }
+        JS_SetReservedSlot(obj, self.index, &PrivateValue(pointer));
+        Ok(())
+    }
+
+    /// Resets the slot of `obj` to `undefined`. Fails if `obj` is not an
+    /// instance of the class.
+    pub unsafe fn clear(&self, obj: *mut JSObject) -> Result<(), ()> {
+        if !is_instance(obj, self.class) {
+            return Err(());
+        }
+        JS_SetReservedSlot(obj, self.index, &UndefinedValue());
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::conversions::{AsBigInt, ConversionBehavior, FromJSValConvertible, ToJSValConvertible};
+use mozjs::jsapi::{
+    InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption,
+};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn bigint_conversion() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        // 64-bit integers round-trip losslessly through BigInts.
+        AsBigInt(i64::MIN).to_jsval(context, rval.handle_mut());
+        assert!(rval.is_bigint());
+        let converted =
+            AsBigInt::<i64>::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+        assert_eq!(
+            converted.unwrap().get_success_value(),
+            Some(&AsBigInt(i64::MIN))
+        );
+
+        // Plain 64-bit integers convert from Numbers by default...
+        assert!(i64::from_jsval(context, rval.handle(), ConversionBehavior::Default).is_err());
+        assert!(mozjs::jsapi::JS_IsExceptionPending(context));
+        mozjs::jsapi::JS_ClearPendingException(context);
+
+        // ...and from BigInts on request.
+        let converted = i64::from_jsval(context, rval.handle(), ConversionBehavior::BigInt);
+        assert_eq!(converted.unwrap().get_success_value(), Some(&i64::MIN));
+
+        AsBigInt(u64::MAX).to_jsval(context, rval.handle_mut());
+        let converted =
+            AsBigInt::<u64>::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+        assert_eq!(
+            converted.unwrap().get_success_value(),
+            Some(&AsBigInt(u64::MAX))
+        );
+        let converted = u64::from_jsval(context, rval.handle(), ConversionBehavior::BigInt);
+        assert_eq!(converted.unwrap().get_success_value(), Some(&u64::MAX));
+
+        for &value in &[0, -1, i128::MIN, i128::MAX, u64::MAX as i128 + 1] {
+            value.to_jsval(context, rval.handle_mut());
+            let converted = i128::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+            assert_eq!(converted.unwrap().get_success_value(), Some(&value));
+        }
+        u128::MAX.to_jsval(context, rval.handle_mut());
+        let converted = u128::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+        assert_eq!(converted.unwrap().get_success_value(), Some(&u128::MAX));
+
+        // Out of range values wrap, clamp or throw.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "2n ** 64n + 5n",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        let converted =
+            AsBigInt::<u64>::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+        assert_eq!(converted.unwrap().get_success_value(), Some(&AsBigInt(5)));
+        let converted =
+            AsBigInt::<i64>::from_jsval(context, rval.handle(), ConversionBehavior::Clamp);
+        assert_eq!(
+            converted.unwrap().get_success_value(),
+            Some(&AsBigInt(i64::MAX))
+        );
+        assert!(AsBigInt::<u64>::from_jsval(
+            context,
+            rval.handle(),
+            ConversionBehavior::EnforceRange
+        )
+        .is_err());
+        assert!(mozjs::jsapi::JS_IsExceptionPending(context));
+        mozjs::jsapi::JS_ClearPendingException(context);
+
+        // Numbers must be integral to convert to BigInts.
+        assert!(runtime
+            .evaluate_script(global.handle(), "-7", "test", 1, rval.handle_mut())
+            .is_ok());
+        let converted =
+            AsBigInt::<i64>::from_jsval(context, rval.handle(), ConversionBehavior::Default);
+        assert_eq!(converted.unwrap().get_success_value(), Some(&AsBigInt(-7)));
+        assert!(runtime
+            .evaluate_script(global.handle(), "1.5", "test", 1, rval.handle_mut())
+            .is_ok());
+        assert!(
+            AsBigInt::<i64>::from_jsval(context, rval.handle(), ConversionBehavior::Default)
+                .is_err()
+        );
+        mozjs::jsapi::JS_ClearPendingException(context);
+    }
+}
@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::cell::Cell;
+use std::ptr;
+
+use mozjs::class::{new_instance, unwrap_private, ClassBuilder, NativeClass};
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::error::JsError;
+use mozjs::jsapi::{GCReason, JSAutoRealm, JSTracer, JS_NewGlobalObject, JS_GC};
+use mozjs::jsapi::{JS_NewPlainObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, Trace, SIMPLE_GLOBAL_CLASS};
+
+thread_local!(static DROPPED: Cell<u32> = Cell::new(0));
+
+struct Counter;
+
+struct Count(Cell<i32>);
+
+unsafe impl Trace for Count {
+    unsafe fn trace(&self, _: *mut JSTracer) {}
+}
+
+impl Drop for Count {
+    fn drop(&mut self) {
+        DROPPED.with(|dropped| dropped.set(dropped.get() + 1));
+    }
+}
+
+impl NativeClass for Counter {
+    type Private = Count;
+    const NAME: &'static str = "Counter";
+}
+
+fn new_count(start: i32) -> Result<Count, JsError> {
+    if start < 0 {
+        return Err(JsError::RangeError("start must not be negative".to_owned()));
+    }
+    Ok(Count(Cell::new(start)))
+}
+
+#[test]
+fn class() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        rooted!(in(context) let proto = ClassBuilder::<Counter>::new()
+            .constructor(new_count)
+            .method("increment", |count: &Count, step: i32| {
+                count.0.set(count.0.get() + step)
+            })
+            .accessor(
+                "count",
+                |count: &Count| count.0.get(),
+                |count: &Count, value: i32| count.0.set(value),
+            )
+            .init(context, global.handle())
+            .unwrap());
+
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        let error = |script: &str| -> String {
+            eval(&format!(
+                "try {{ {}; 'no error' }} catch (e) {{ e.name + ': ' + e.message }}",
+                script
+            ))
+        };
+
+        assert_eq!(
+            eval("let c = new Counter(1); c.increment(2); String(c.count)"),
+            "3"
+        );
+        assert_eq!(eval("c.count = 10; String(c.count)"), "10");
+        assert_eq!(eval("String(c instanceof Counter)"), "true");
+        assert_eq!(
+            error("Counter(1)"),
+            "TypeError: Counter constructor requires 'new'"
+        );
+        assert_eq!(
+            error("new Counter(-1)"),
+            "RangeError: start must not be negative"
+        );
+        assert_eq!(
+            error("Counter.prototype.increment.call({}, 1)"),
+            "TypeError: 'this' is not a Counter"
+        );
+        assert_eq!(
+            error("Counter.prototype.count"),
+            "TypeError: 'this' is not a Counter"
+        );
+
+        // Instances can be created and inspected from Rust.
+        rooted!(in(context) let instance =
+            new_instance::<Counter>(context, proto.handle(), Count(Cell::new(7))).unwrap());
+        assert_eq!(
+            unwrap_private::<Counter>(instance.get()).unwrap().0.get(),
+            7
+        );
+        rooted!(in(context) let plain = JS_NewPlainObject(context));
+        assert!(unwrap_private::<Counter>(plain.get()).is_none());
+        assert!(unwrap_private::<Counter>(proto.get()).is_none());
+
+        // Private data is dropped when instances are finalized.
+        eval("c = null; ''");
+        JS_GC(context, GCReason::API);
+        assert!(DROPPED.with(|dropped| dropped.get()) >= 1);
+    }
+}
@@ -0,0 +1,113 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::function::{new_closure, new_traced_closure};
+use mozjs::jsapi::{GCReason, Heap, JSAutoRealm, JSObject, JS_GetFunctionObject, JS_GC};
+use mozjs::jsapi::{JS_NewGlobalObject, JS_NewPlainObject, OnNewGlobalHookOption};
+use mozjs::jsval::{Int32Value, ObjectValue, UndefinedValue};
+use mozjs::rust::wrappers::JS_DefineProperty;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+struct DropFlag(Rc<Cell<bool>>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.set(true);
+    }
+}
+
+#[test]
+fn closure() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        let define = |name: &str, function: *mut JSObject| {
+            rooted!(in(context) let value = ObjectValue(function));
+            let name = format!("{}\0", name);
+            assert!(JS_DefineProperty(
+                context,
+                global.handle(),
+                name.as_ptr() as *const _,
+                value.handle(),
+                0,
+            ));
+        };
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+
+        // Captured state is shared with Rust.
+        let counter = Rc::new(Cell::new(0));
+        let dropped = Rc::new(Cell::new(false));
+        let captured = counter.clone();
+        let flag = DropFlag(dropped.clone());
+        let increment = new_closure(context, "increment", move |step: i32| {
+            let _ = &flag;
+            captured.set(captured.get() + step);
+            captured.get()
+        })
+        .unwrap();
+        define("increment", JS_GetFunctionObject(increment));
+        assert_eq!(eval("increment(2); String(increment(3))"), "5");
+        assert_eq!(eval("increment.name + increment.length"), "increment1");
+        assert_eq!(counter.get(), 5);
+
+        // Traced state keeps GC things alive.
+        let state = Rc::new(Heap::default());
+        {
+            rooted!(in(context) let object = JS_NewPlainObject(context));
+            rooted!(in(context) let answer = Int32Value(42));
+            assert!(JS_DefineProperty(
+                context,
+                object.handle(),
+                b"answer\0".as_ptr() as *const _,
+                answer.handle(),
+                0,
+            ));
+            state.set(object.get());
+            let get =
+                new_traced_closure(context, "get", state, |state: &Rc<Heap<*mut JSObject>>| {
+                    state.get()
+                })
+                .unwrap();
+            define("get", JS_GetFunctionObject(get));
+        }
+        JS_GC(context, GCReason::API);
+        assert_eq!(eval("String(get().answer)"), "42");
+
+        // The closure is dropped once the function is collected.
+        assert!(!dropped.get());
+        eval("delete increment; ''");
+        JS_GC(context, GCReason::API);
+        assert!(dropped.get());
+    }
+}
@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ptr;
+
+use mozjs::collections::{AsMap, JSMap, JSSet};
+use mozjs::conversions::{
+    ConversionBehavior, ConversionResult, FromJSValConvertible, ToJSValConvertible,
+};
+use mozjs::jsapi::{
+    InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption,
+};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn collections() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        let mut orig_map = BTreeMap::new();
+        orig_map.insert("a".to_owned(), 1);
+        orig_map.insert("b".to_owned(), 2);
+        let map = JSMap::from_entries(context, &orig_map).unwrap();
+        assert_eq!(map.len(context), 2);
+        assert!(map.has(context, "a").unwrap());
+        assert_eq!(
+            map.get::<_, i32>(context, "b", ConversionBehavior::Default)
+                .unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            map.get::<_, i32>(context, "c", ConversionBehavior::Default)
+                .unwrap(),
+            None
+        );
+        map.set(context, "c", &3).unwrap();
+        assert!(map.delete(context, "a").unwrap());
+        assert!(!map.delete(context, "a").unwrap());
+        let entries = map
+            .entries::<String, i32>(context, (), ConversionBehavior::Default)
+            .unwrap()
+            .collect::<Result<Vec<_>, ()>>()
+            .unwrap();
+        assert_eq!(entries, [("b".to_owned(), 2), ("c".to_owned(), 3)]);
+
+        // Maps made in script can be wrapped and read back.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "new Map([[1, 'one'], [2, 'two']])",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        let map = match JSMap::from_jsval(context, rval.handle(), ()).unwrap() {
+            ConversionResult::Success(map) => map,
+            ConversionResult::Failure(_) => panic!("a Map was not converted"),
+        };
+        assert_eq!(
+            map.get::<_, String>(context, &1, ()).unwrap(),
+            Some("one".to_owned())
+        );
+        map.clear(context).unwrap();
+        assert!(map.is_empty(context));
+
+        // Wrapped `HashMap`s convert to and from Maps, with keys of any type.
+        let mut orig_map = HashMap::new();
+        orig_map.insert(1, "one".to_owned());
+        orig_map.insert(2, "two".to_owned());
+        AsMap(orig_map.clone()).to_jsval(context, rval.handle_mut());
+        assert!(JSMap::from_jsval(context, rval.handle(), ())
+            .unwrap()
+            .get_success_value()
+            .is_some());
+        let converted = AsMap::<HashMap<i32, String>>::from_jsval(
+            context,
+            rval.handle(),
+            (ConversionBehavior::Default, ()),
+        )
+        .unwrap();
+        assert_eq!(converted.get_success_value(), Some(&AsMap(orig_map)));
+
+        let mut orig_set = HashSet::new();
+        orig_set.insert(1);
+        orig_set.insert(5);
+        orig_set.to_jsval(context, rval.handle_mut());
+        let converted =
+            HashSet::<i32>::from_jsval(context, rval.handle(), ConversionBehavior::Default)
+                .unwrap();
+        assert_eq!(&orig_set, converted.get_success_value().unwrap());
+
+        let set = JSSet::new(context).unwrap();
+        set.add(context, "x").unwrap();
+        set.add(context, "x").unwrap();
+        set.add(context, "y").unwrap();
+        assert_eq!(set.len(context), 2);
+        assert!(set.has(context, "y").unwrap());
+        assert!(set.delete(context, "y").unwrap());
+        let values = set
+            .values::<String>(context, ())
+            .unwrap()
+            .collect::<Result<Vec<_>, ()>>()
+            .unwrap();
+        assert_eq!(values, ["x"]);
+
+        // Arrays are not sets.
+        assert!(runtime
+            .evaluate_script(global.handle(), "[1, 2]", "test", 1, rval.handle_mut())
+            .is_ok());
+        match HashSet::<i32>::from_jsval(context, rval.handle(), ConversionBehavior::Default)
+            .unwrap()
+        {
+            ConversionResult::Failure(_) => {}
+            ConversionResult::Success(_) => panic!("an array was converted to a set"),
+        }
+    }
+}
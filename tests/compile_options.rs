@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ffi::CStr;
+use std::ptr;
+
+use mozjs::glue::JS_GetScriptPrivate;
+use mozjs::jsapi::JS_ExecuteScript;
+use mozjs::jsapi::{Compile1, JSAutoRealm, JSExnType, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::{Int32Value, UndefinedValue};
+use mozjs::rust::{transform_str_to_source_text, CompileOptionsBuilder, JSEngine, RealmOptions};
+use mozjs::rust::{Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn compile_options() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        // Sloppy mode allows assigning to undeclared variables...
+        let options = CompileOptionsBuilder::new("sloppy.js", 1);
+        assert!(runtime
+            .evaluate_script_with_options(global.handle(), "x = 1", &options, rval.handle_mut())
+            .is_ok());
+
+        // ...but forced strict mode doesn't, and errors report the position.
+        let options = CompileOptionsBuilder::new("strict.js", 10)
+            .column(4)
+            .force_strict_mode(true)
+            .introduction_type(CStr::from_bytes_with_nul(b"eval\0").unwrap())
+            .source_map_url("strict.js.map")
+            .lazy_parsing(false);
+        let error = runtime
+            .evaluate_script_with_options(global.handle(), "y = 1", &options, rval.handle_mut())
+            .unwrap_err();
+        assert_eq!(error.exn_type, Some(JSExnType::JSEXN_REFERENCEERR));
+        assert_eq!(error.filename, "strict.js");
+        assert_eq!(error.line, 10);
+        assert_eq!(error.column, 4);
+
+        let options = CompileOptionsBuilder::new("norval.js", 1).no_script_rval(true);
+        rval.set(Int32Value(1));
+        assert!(runtime
+            .evaluate_script_with_options(global.handle(), "42", &options, rval.handle_mut())
+            .is_ok());
+        assert!(rval.is_undefined());
+
+        rooted!(in(context) let private = Int32Value(5));
+        let options = CompileOptionsBuilder::new("private.js", 1).private_value(private.handle());
+        let script = runtime
+            .compile_script_with_options(global.handle(), "1 + 1", &options)
+            .unwrap();
+        JS_GetScriptPrivate(script.handle().get(), rval.handle_mut().into());
+        assert_eq!(rval.get().to_int32(), 5);
+        assert!(runtime
+            .evaluate_script_with_options(global.handle(), "2 + 2", &options, rval.handle_mut())
+            .is_ok());
+        assert_eq!(rval.get().to_int32(), 4);
+
+        // Options built by hand need the debug metadata attached explicitly.
+        let wrapper = options.build(context);
+        let mut source = transform_str_to_source_text("3 + 3");
+        rooted!(in(context) let compiled = Compile1(context, wrapper.ptr, &mut source));
+        assert!(!compiled.is_null());
+        assert!(options.update_debug_metadata(context, compiled.handle(), &wrapper));
+        assert!(JS_ExecuteScript(
+            context,
+            compiled.handle().into(),
+            rval.handle_mut().into()
+        ));
+        assert_eq!(rval.get().to_int32(), 6);
+        JS_GetScriptPrivate(compiled.get(), rval.handle_mut().into());
+        assert_eq!(rval.get().to_int32(), 5);
+
+        // NUL characters in the filename are dropped rather than panicking.
+        let options = CompileOptionsBuilder::new("nul\0.js", 1);
+        let error = runtime
+            .evaluate_script_with_options(global.handle(), "(", &options, rval.handle_mut())
+            .unwrap_err();
+        assert_eq!(error.filename, "nul.js");
+    }
+}
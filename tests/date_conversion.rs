@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[macro_use]
+extern crate mozjs;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible, ToJSValConvertible};
+use mozjs::glue::IsWrapper;
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsapi::{InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, JS_WrapValue};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn date_conversion() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        for &time in &[
+            UNIX_EPOCH + Duration::from_millis(1_600_000_000_123),
+            UNIX_EPOCH - Duration::from_millis(86_400_000),
+        ] {
+            time.to_jsval(context, rval.handle_mut());
+            let converted = SystemTime::from_jsval(context, rval.handle(), ()).unwrap();
+            assert_eq!(converted.get_success_value(), Some(&time));
+        }
+
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "new Date(Date.UTC(2020, 0, 1))",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        let converted = SystemTime::from_jsval(context, rval.handle(), ()).unwrap();
+        assert_eq!(
+            converted.get_success_value(),
+            Some(&(UNIX_EPOCH + Duration::from_secs(1_577_836_800)))
+        );
+
+        #[cfg(feature = "chrono")]
+        {
+            let converted = DateTime::<Utc>::from_jsval(context, rval.handle(), ()).unwrap();
+            assert_eq!(
+                converted.get_success_value().map(DateTime::timestamp),
+                Some(1_577_836_800)
+            );
+
+            let time = DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_millis(1_600_000_000_123));
+            time.to_jsval(context, rval.handle_mut());
+            let converted = DateTime::<Utc>::from_jsval(context, rval.handle(), ()).unwrap();
+            assert_eq!(converted.get_success_value(), Some(&time));
+        }
+
+        for script in &["new Date(NaN)", "({})", "0"] {
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test", 1, rval.handle_mut())
+                .is_ok());
+            match SystemTime::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Failure(_) => {}
+                ConversionResult::Success(_) => panic!("{} was converted to a time", script),
+            }
+        }
+
+        // Dates from other compartments are unwrapped.
+        rooted!(in(context) let other_global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            OnNewGlobalHookOption::FireOnNewGlobalHook,
+            &*c_option,
+        ));
+        {
+            let _ac = JSAutoRealm::new(context, other_global.get());
+            assert!(InitRealmStandardClasses(context));
+        }
+        assert!(runtime
+            .evaluate_script(
+                other_global.handle(),
+                "new Date(0)",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        assert!(JS_WrapValue(context, rval.handle_mut().into()));
+        assert!(IsWrapper(rval.to_object()));
+        let converted = SystemTime::from_jsval(context, rval.handle(), ()).unwrap();
+        assert_eq!(converted.get_success_value(), Some(&UNIX_EPOCH));
+    }
+}
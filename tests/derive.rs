@@ -0,0 +1,138 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible, ToJSValConvertible};
+use mozjs::jsapi::{
+    InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption,
+};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[derive(Debug, PartialEq, FromJSValConvertible, ToJSValConvertible)]
+enum Mode {
+    #[js(rename = "read-only")]
+    ReadOnly,
+    Write,
+}
+
+#[derive(Debug, PartialEq, FromJSValConvertible, ToJSValConvertible)]
+struct Options {
+    name: String,
+    #[js(rename = "maxSize")]
+    max_size: u32,
+    mode: Mode,
+    label: Option<String>,
+    #[js(default)]
+    verbose: bool,
+}
+
+#[derive(Debug, PartialEq, FromJSValConvertible, ToJSValConvertible)]
+struct Pair<T> {
+    first: T,
+    second: Option<T>,
+}
+
+#[test]
+fn derive() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "({ name: 'test', maxSize: 16, mode: 'read-only' })",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        let options = Options::from_jsval(context, rval.handle(), ()).unwrap();
+        let expected = Options {
+            name: "test".to_owned(),
+            max_size: 16,
+            mode: Mode::ReadOnly,
+            label: None,
+            verbose: false,
+        };
+        assert_eq!(options.get_success_value(), Some(&expected));
+
+        // Round trip through a plain object.
+        let options = Options {
+            mode: Mode::Write,
+            label: Some("label".to_owned()),
+            ..expected
+        };
+        options.to_jsval(context, rval.handle_mut());
+        assert!(rval.is_object());
+        let converted = Options::from_jsval(context, rval.handle(), ()).unwrap();
+        assert_eq!(converted.get_success_value(), Some(&options));
+
+        // Missing required members, invalid members and enum values fail.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "({ name: 'test' })",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        match Options::from_jsval(context, rval.handle(), ()).unwrap() {
+            ConversionResult::Failure(message) => assert!(message.contains("maxSize")),
+            ConversionResult::Success(_) => panic!("missing member was accepted"),
+        }
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "({ name: 'test', maxSize: 1, mode: 'append' })",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        match Options::from_jsval(context, rval.handle(), ()).unwrap() {
+            ConversionResult::Failure(message) => {
+                assert!(message.contains("mode"));
+                assert!(message.contains("append"));
+            }
+            ConversionResult::Success(_) => panic!("invalid enum value was accepted"),
+        }
+        // Generic structs convert through the conversions of their parameters.
+        let pair = Pair {
+            first: Mode::Write,
+            second: Some(Mode::ReadOnly),
+        };
+        pair.to_jsval(context, rval.handle_mut());
+        let converted = Pair::<Mode>::from_jsval(context, rval.handle(), ()).unwrap();
+        assert_eq!(converted.get_success_value(), Some(&pair));
+
+        assert!(runtime
+            .evaluate_script(global.handle(), "42", "test", 1, rval.handle_mut())
+            .is_ok());
+        assert!(Options::from_jsval(context, rval.handle(), ())
+            .unwrap()
+            .get_success_value()
+            .is_none());
+    }
+}
@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+extern crate anyhow;
+
+use std::ptr;
+use std::thread;
+
+use mozjs::error::{DetachedException, JSException};
+use mozjs::jsapi::{JSAutoRealm, JSExnType, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{HandleObject, JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+fn evaluate(runtime: &Runtime, global: HandleObject, script: &str) -> anyhow::Result<()> {
+    rooted!(in(runtime.cx()) let mut rval = UndefinedValue());
+    runtime
+        .evaluate_script_with_exception(global, script, "test.js", 1, rval.handle_mut())
+        .map_err(JSException::detach)?;
+    Ok(())
+}
+
+#[test]
+fn detached_exception() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        assert!(evaluate(&runtime, global.handle(), "1 + 1").is_ok());
+
+        let error =
+            evaluate(&runtime, global.handle(), "\nthrow new RangeError('far')").unwrap_err();
+        let detached = error
+            .downcast::<DetachedException>()
+            .expect("not a DetachedException");
+        assert_eq!(detached.message, "far");
+        assert_eq!(detached.filename, "test.js");
+        assert_eq!(detached.line, 2);
+        assert_eq!(detached.exn_type, Some(JSExnType::JSEXN_RANGEERR));
+        assert!(!detached.is_uncatchable());
+
+        // Detached exceptions can cross threads.
+        let message = thread::spawn(move || detached.to_string()).join().unwrap();
+        assert!(message.starts_with("test.js:2:"));
+        assert!(message.ends_with(": far"));
+    }
+}
@@ -0,0 +1,86 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::cell::RefCell;
+use std::ptr;
+use std::rc::Rc;
+
+use mozjs::error::ErrorReport;
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsapi::{JSAutoRealm, JSContext, JSExnType, JS_NewGlobalObject};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::wrappers::JS_ErrorFromException;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn error_report() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        // Errors carry the offending source line and notes.
+        rooted!(in(context) let mut rval = UndefinedValue());
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "try { eval('let x; let x;') } catch (e) { e }",
+                "test.js",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        rooted!(in(context) let error = rval.to_object());
+        let report = JS_ErrorFromException(context, error.handle());
+        assert!(!report.is_null());
+        let report = ErrorReport::from_raw(&*report);
+        assert_eq!(report.exn_type, Some(JSExnType::JSEXN_SYNTAXERR));
+        assert!(!report.is_warning);
+        assert!(report.message.contains("redeclaration"));
+        assert_eq!(
+            report.source_line.as_ref().map(|s| &**s),
+            Some("let x; let x;")
+        );
+        assert!(report.token_offset <= "let x; let x;".len());
+        assert_eq!(report.notes.len(), 1);
+        assert_eq!(report.notes[0].line, 1);
+
+        // Warnings go to the installed closure.
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let reported = warnings.clone();
+        runtime.set_warning_reporter(move |cx: *mut JSContext, report: &ErrorReport| {
+            assert_eq!(cx, context);
+            reported.borrow_mut().push(report.clone())
+        });
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "function f() { 'use asm'; return 1; }",
+                "asm.js",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        let warnings = warnings.borrow();
+        assert!(!warnings.is_empty());
+        assert!(warnings[0].is_warning);
+        assert_eq!(warnings[0].exn_type, None);
+        assert_eq!(warnings[0].filename, "asm.js");
+        assert!(warnings[0].message.starts_with("asm.js"));
+    }
+}
@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::jsapi::{JSAutoRealm, JSExnType, JS_IsExceptionPending, JS_NewGlobalObject};
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn exception() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+        let error = runtime
+            .evaluate_script_with_exception(
+                global.handle(),
+                "function f() {\n  throw new TypeError('boom');\n}\nf();",
+                "test.js",
+                1,
+                rval.handle_mut(),
+            )
+            .unwrap_err();
+        assert!(!JS_IsExceptionPending(context));
+        assert!(!error.is_uncatchable());
+        assert_eq!(error.message, "boom");
+        assert_eq!(error.filename, "test.js");
+        assert_eq!(error.line, 2);
+        assert_eq!(error.exn_type, Some(JSExnType::JSEXN_TYPEERR));
+        assert!(error.value().is_object());
+        assert!(error.stack.as_ref().unwrap().contains("f@test.js:2"));
+        assert_eq!(error.to_string(), format!("test.js:2:{}: boom", error.column));
+
+        let error = runtime
+            .evaluate_script_with_exception(global.handle(), "throw 42", "test.js", 1, rval.handle_mut())
+            .unwrap_err();
+        assert_eq!(error.value().get().to_int32(), 42);
+        assert_eq!(error.message, "42");
+        assert_eq!(error.exn_type, None);
+        assert!(error.stack.is_none());
+
+        assert!(runtime
+            .evaluate_script_with_exception(global.handle(), "1 + 1", "test.js", 1, rval.handle_mut())
+            .is_ok());
+    }
+}
@@ -0,0 +1,95 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::env;
+use std::fs;
+use std::os::raw::c_char;
+use std::process;
+use std::ptr;
+
+use mozjs::jsapi::{JSAutoRealm, JS_GetProperty, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::module::FilesystemLoader;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn filesystem_loader() {
+    // <temp>/outside.js must stay out of reach of modules under <temp>/root.
+    let directory = env::temp_dir().join(format!("mozjs-filesystem-loader-{}", process::id()));
+    let root = directory.join("root");
+    fs::create_dir_all(root.join("lib")).unwrap();
+    let outside = directory.join("outside.js");
+    fs::write(&outside, "export const secret = 1;").unwrap();
+    fs::write(
+        root.join("main.js"),
+        "import { double } from './lib/math.js';\nexport const answer = double(21);",
+    )
+    .unwrap();
+    fs::write(
+        root.join("lib/math.js"),
+        "import '../lib/unit.js';\nexport function double(x) { return x * 2; }",
+    )
+    .unwrap();
+    fs::write(root.join("lib/unit.js"), "").unwrap();
+    fs::write(root.join("escape.js"), "import '../outside.js';").unwrap();
+    fs::write(
+        root.join("absolute.js"),
+        format!("import '{}';", outside.display()),
+    )
+    .unwrap();
+
+    assert!(FilesystemLoader::new(directory.join("missing")).is_err());
+
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+    runtime.set_module_loader(FilesystemLoader::new(&root).unwrap());
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        // Relative imports, including ones that leave and reenter a
+        // directory under the root.
+        let namespace = runtime.evaluate_module(global.handle(), "main.js").unwrap();
+        assert!(JS_GetProperty(
+            context,
+            namespace.handle().into(),
+            b"answer\0".as_ptr() as *const c_char,
+            rval.handle_mut().into(),
+        ));
+        assert_eq!(rval.get().to_int32(), 42);
+
+        let error = runtime
+            .evaluate_module(global.handle(), "escape.js")
+            .unwrap_err();
+        assert_eq!(error.message, "../outside.js points outside of the root");
+        let error = runtime
+            .evaluate_module(global.handle(), "../outside.js")
+            .unwrap_err();
+        assert_eq!(error.message, "../outside.js points outside of the root");
+
+        let error = runtime
+            .evaluate_module(global.handle(), "absolute.js")
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            format!("{} is not relative to the root", outside.display())
+        );
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,105 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::error::JsError;
+use mozjs::function::define_function;
+use mozjs::jsapi::{JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn greet(name: String) -> String {
+    format!("Hello, {}!", name)
+}
+
+fn repeat(text: String, times: Option<u32>) -> String {
+    text.repeat(times.unwrap_or(2) as usize)
+}
+
+fn sqrt(x: f64) -> Result<f64, JsError> {
+    if x < 0.0 {
+        return Err(JsError::RangeError("negative".to_owned()));
+    }
+    Ok(x.sqrt())
+}
+
+fn fail() {
+    panic!("fail() was called");
+}
+
+#[test]
+fn function() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        define_function(context, global.handle(), "add", add).unwrap();
+        define_function(context, global.handle(), "greet", greet).unwrap();
+        define_function(context, global.handle(), "repeat", repeat).unwrap();
+        define_function(context, global.handle(), "sqrt", sqrt).unwrap();
+        define_function(context, global.handle(), "fail", fail).unwrap();
+        define_function(context, global.handle(), "answer", || 42).unwrap();
+
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        let error = |call: &str| -> String {
+            eval(&format!(
+                "try {{ {}; 'no error' }} catch (e) {{ e.name + ': ' + e.message }}",
+                call
+            ))
+        };
+
+        assert_eq!(eval("String(add(2, 3))"), "5");
+        assert_eq!(eval("String(add.length)"), "2");
+        assert_eq!(eval("greet('JS')"), "Hello, JS!");
+        assert_eq!(eval("repeat('a', 3)"), "aaa");
+        assert_eq!(eval("repeat('a')"), "aa");
+        assert_eq!(eval("String(repeat.length)"), "1");
+        assert_eq!(eval("String(sqrt(9))"), "3");
+        assert_eq!(eval("String(answer())"), "42");
+        assert_eq!(
+            error("add(1)"),
+            "TypeError: At least 2 arguments required, but only 1 passed"
+        );
+        assert_eq!(error("sqrt(-1)"), "RangeError: negative");
+        assert!(error("add(1, Symbol())").starts_with("TypeError: "));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _ =
+                runtime.evaluate_script(global.handle(), "fail()", "test.js", 0, rval.handle_mut());
+        }));
+        assert!(result.is_err());
+    }
+}
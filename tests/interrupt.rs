@@ -0,0 +1,95 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::cell::Cell;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mozjs::jsapi::{JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn interrupt() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        // An interrupt requested from another thread runs the callbacks,
+        // and returning false from one of them stops the script.
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        runtime.add_interrupt_callback(move |_| {
+            counter.set(counter.get() + 1);
+            counter.get() != 2
+        });
+        let handle = runtime.interrupt_handle();
+        let done = Arc::new(AtomicBool::new(false));
+        let finished = done.clone();
+        // Keep requesting until the script stops, so that requests that
+        // arrive before it starts, or together, don't matter.
+        let requester = thread::spawn(move || {
+            while !finished.load(Ordering::SeqCst) {
+                assert!(handle.request_interrupt());
+                thread::yield_now();
+            }
+        });
+        let error = runtime
+            .evaluate_script_with_exception(
+                global.handle(),
+                "while (true) {}",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .unwrap_err();
+        done.store(true, Ordering::SeqCst);
+        requester.join().unwrap();
+        assert_eq!(calls.get(), 2);
+        assert!(error.is_uncatchable());
+        assert!(!error.is_timed_out());
+
+        runtime.set_execution_time_limit(Some(Duration::from_millis(100)));
+        let start = Instant::now();
+        let error = runtime
+            .evaluate_script_with_exception(
+                global.handle(),
+                "while (true) {}",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(error.is_uncatchable());
+        assert!(error.is_timed_out());
+
+        // Scripts finishing within the limit are unaffected.
+        assert!(runtime
+            .evaluate_script_with_exception(global.handle(), "1 + 1", "test", 1, rval.handle_mut())
+            .is_ok());
+        assert_eq!(rval.get().to_int32(), 2);
+
+        runtime.set_execution_time_limit(None);
+    }
+}
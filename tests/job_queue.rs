@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::jsapi::{JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn job_queue() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        assert!(!runtime.has_pending_jobs());
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "var log = [];
+                 Promise.resolve(1).then(v => log.push(v));
+                 Promise.reject(new Error('ignored')).then(null, () => { throw 'uncaught'; });
+                 (async function() {
+                     await null;
+                     log.push(2);
+                     await null;
+                     log.push(3);
+                 })();
+                 log.length",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        assert_eq!(rval.get().to_int32(), 0);
+        assert!(runtime.has_pending_jobs());
+
+        runtime.run_jobs();
+        assert!(!runtime.has_pending_jobs());
+
+        // Jobs enqueued by other jobs ran in the same checkpoint, and the
+        // exception thrown by one of them did not stop the others.
+        assert!(runtime
+            .evaluate_script(global.handle(), "log.join()", "test", 1, rval.handle_mut())
+            .is_ok());
+        let log = mozjs::conversions::jsstr_to_string(context, rval.get().to_string());
+        assert_eq!(log, "1,2,3");
+    }
+}
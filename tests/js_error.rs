@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::error::{JSException, JsError};
+use mozjs::function::define_function;
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsapi::{JSAutoRealm, JS_IsExceptionPending, JS_NewGlobalObject};
+use mozjs::jsval::{Int32Value, UndefinedValue};
+use mozjs::rust::wrappers::JS_DefineProperty;
+use mozjs::rust::{Handle, JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+fn positive(x: i32) -> Result<i32, JsError> {
+    let result = if x > 0 {
+        ConversionResult::Success(x)
+    } else {
+        ConversionResult::Failure("not positive".into())
+    };
+    result.into_result()
+}
+
+fn throw_number() -> Result<(), JsError> {
+    let value = Int32Value(7);
+    Err(JsError::from_value(unsafe {
+        Handle::from_marked_location(&value)
+    }))
+}
+
+fn out_of_memory() -> Result<(), JsError> {
+    Err(JsError::OutOfMemory)
+}
+
+fn terminate() -> Result<(), JsError> {
+    Err(JsError::Uncatchable)
+}
+
+#[test]
+fn js_error() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        define_function(context, global.handle(), "positive", positive).unwrap();
+        define_function(context, global.handle(), "throwNumber", throw_number).unwrap();
+        define_function(context, global.handle(), "outOfMemory", out_of_memory).unwrap();
+        define_function(context, global.handle(), "terminate", terminate).unwrap();
+
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        let exception = |script: &str| -> JSException {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            runtime
+                .evaluate_script_with_exception(
+                    global.handle(),
+                    script,
+                    "test.js",
+                    0,
+                    rval.handle_mut(),
+                )
+                .unwrap_err()
+        };
+
+        assert_eq!(eval("String(positive(1))"), "1");
+        assert_eq!(
+            eval("try { positive(0) } catch (e) { e.name + ': ' + e.message }"),
+            "TypeError: not positive"
+        );
+        assert_eq!(
+            eval("try { throwNumber() } catch (e) { typeof e + ' ' + e }"),
+            "number 7"
+        );
+
+        let oom = exception("outOfMemory()");
+        assert!(!oom.is_uncatchable());
+        assert_eq!(oom.message, "out of memory");
+
+        assert!(exception("try { terminate() } catch (e) {}").is_uncatchable());
+        assert!(!JS_IsExceptionPending(context));
+
+        // Exceptions taken from the context can be thrown again.
+        let thrown = exception("throw { code: 3 }");
+        JsError::from(thrown).throw(context);
+        let taken = JSException::take(context).expect("no pending exception");
+        assert!(JS_DefineProperty(
+            context,
+            global.handle(),
+            b"e\0".as_ptr() as *const _,
+            taken.value(),
+            0,
+        ));
+        assert_eq!(eval("String(e.code)"), "3");
+
+        let uncatchable = exception("terminate()");
+        JsError::from(uncatchable).throw(context);
+        assert!(!JS_IsExceptionPending(context));
+    }
+}
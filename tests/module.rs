@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::os::raw::c_char;
+use std::ptr;
+
+use mozjs::jsapi::{JSAutoRealm, JS_GetProperty, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::module::MapLoader;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn module() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    let mut loader = MapLoader::new();
+    loader.insert(
+        "main.js",
+        "import { double } from './lib/math.js';\nexport const answer = double(21);",
+    );
+    loader.insert("lib/math.js", "export function double(x) { return x * 2; }");
+    loader.insert("broken.js", "import './missing.js';");
+    loader.insert("escape.js", "import '../main.js';");
+    runtime.set_module_loader(loader);
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        let namespace = runtime.evaluate_module(global.handle(), "main.js").unwrap();
+        assert!(JS_GetProperty(
+            context,
+            namespace.handle().into(),
+            b"answer\0".as_ptr() as *const c_char,
+            rval.handle_mut().into(),
+        ));
+        assert_eq!(rval.get().to_int32(), 42);
+
+        let namespace = runtime
+            .evaluate_module_source(global.handle(), "inline.js", "export default 7;")
+            .unwrap();
+        assert!(JS_GetProperty(
+            context,
+            namespace.handle().into(),
+            b"default\0".as_ptr() as *const c_char,
+            rval.handle_mut().into(),
+        ));
+        assert_eq!(rval.get().to_int32(), 7);
+
+        // Dynamic imports from scripts share the module map.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "var result; import('lib/math.js').then(m => result = m.double(5));",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        runtime.run_jobs();
+        assert!(runtime
+            .evaluate_script(global.handle(), "result", "test", 1, rval.handle_mut())
+            .is_ok());
+        assert_eq!(rval.get().to_int32(), 10);
+
+        let error = runtime.evaluate_module(global.handle(), "missing.js").unwrap_err();
+        assert_eq!(error.message, "cannot find module missing.js");
+        let error = runtime.evaluate_module(global.handle(), "broken.js").unwrap_err();
+        assert_eq!(error.message, "cannot find module missing.js");
+        let error = runtime.evaluate_module(global.handle(), "escape.js").unwrap_err();
+        assert_eq!(error.message, "../main.js points outside of the root");
+    }
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::function::define_function;
+use mozjs::jsapi::{HandleValueArray, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::panic::{maybe_resume_unwind, set_panic_policy, PanicPolicy, Panics};
+use mozjs::rust::wrappers::JS_CallFunctionName;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+fn fail(message: String) {
+    panic!("{}", message);
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .expect("unexpected payload")
+        .clone()
+}
+
+#[test]
+fn panic_policy() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        define_function(context, global.handle(), "fail", fail).unwrap();
+
+        let run = |script: &str| -> Box<dyn Any + Send> {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            catch_unwind(AssertUnwindSafe(|| {
+                let _ = runtime.evaluate_script(
+                    global.handle(),
+                    script,
+                    "test.js",
+                    0,
+                    rval.handle_mut(),
+                );
+            }))
+            .expect_err("no panic")
+        };
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+
+        // By default, panics terminate the script.
+        let payload = run("var caught = 'no'; try { fail('first') } catch (e) { caught = 'yes' }");
+        assert_eq!(message(&*payload), "first");
+        assert_eq!(eval("caught"), "no");
+
+        // Panics can be thrown as InternalErrors, and several of them are
+        // resumed together.
+        set_panic_policy(PanicPolicy::InternalError);
+        let payload = run("var caught = []; \
+             for (let m of ['a', 'b']) { try { fail(m) } catch (e) { caught.push(e.message) } }");
+        let panics = match payload.downcast::<Panics>() {
+            Ok(panics) => panics,
+            Err(_) => panic!("single panic"),
+        };
+        let messages: Vec<String> = panics.0.iter().map(|payload| message(&**payload)).collect();
+        assert_eq!(messages, ["a", "b"]);
+        assert_eq!(
+            eval("caught.join()"),
+            "Rust code panicked: a,Rust code panicked: b"
+        );
+
+        // Raw JSAPI calls leave the panic stored until the caller resumes it.
+        eval("function g() { try { fail('c') } catch (e) {} return 1 } ''");
+        rooted!(in(context) let mut rval = UndefinedValue());
+        assert!(JS_CallFunctionName(
+            context,
+            global.handle(),
+            b"g\0".as_ptr() as *const _,
+            &HandleValueArray::new(),
+            rval.handle_mut(),
+        ));
+        assert_eq!(rval.get().to_int32(), 1);
+        let payload = catch_unwind(maybe_resume_unwind).expect_err("no panic");
+        assert_eq!(message(&*payload), "c");
+    }
+}
@@ -0,0 +1,145 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::future;
+use std::ptr;
+use std::sync::mpsc;
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use mozjs::jsapi::{Heap, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption, PromiseState};
+use mozjs::jsval::{Int32Value, UndefinedValue};
+use mozjs::promise::WakeSource;
+use mozjs::promise::{block_on, future_to_promise, run_until_stalled, JSPromise, Stalled};
+use mozjs::rust::{JSEngine, RealmOptions, RootedTraceableBox, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn promise() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        // Awaiting a JS promise from Rust.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "Promise.resolve(40).then(v => v + 2)",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        rooted!(in(context) let object = rval.to_object());
+        let promise = JSPromise::from_object(object.handle()).unwrap();
+        assert_eq!(promise.state(), PromiseState::Pending);
+        let value = block_on(&runtime, promise.clone()).unwrap().unwrap();
+        assert_eq!(value.get().to_int32(), 42);
+        assert_eq!(promise.state(), PromiseState::Fulfilled);
+
+        // Rejections surface as exceptions.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "(async () => { throw new TypeError('nope'); })()",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        rooted!(in(context) let object = rval.to_object());
+        let promise = JSPromise::from_object(object.handle()).unwrap();
+        let error = block_on(&runtime, promise).unwrap().unwrap_err();
+        assert_eq!(error.message, "nope");
+
+        // Rust futures can be exposed as promises, including ones that
+        // themselves await JS promises.
+        let ready = future::ready(Ok(RootedTraceableBox::from_box(Heap::boxed(Int32Value(7)))));
+        let promise = future_to_promise(context, ready).unwrap();
+        assert_eq!(promise.state(), PromiseState::Pending);
+        let value = block_on(&runtime, promise.clone()).unwrap().unwrap();
+        assert_eq!(value.get().to_int32(), 7);
+        assert_eq!(promise.state(), PromiseState::Fulfilled);
+
+        assert!(runtime
+            .evaluate_script(global.handle(), "Promise.reject(3)", "test", 1, rval.handle_mut())
+            .is_ok());
+        rooted!(in(context) let object = rval.to_object());
+        let inner = JSPromise::from_object(object.handle()).unwrap();
+        let promise = future_to_promise(context, inner).unwrap();
+        let error = block_on(&runtime, promise.clone()).unwrap().unwrap_err();
+        assert_eq!(error.value().to_int32(), 3);
+        assert_eq!(promise.state(), PromiseState::Rejected);
+
+        // Several tasks can await clones of the same promise.
+        let shared = JSPromise::new(context).unwrap();
+        let first = future_to_promise(context, shared.clone()).unwrap();
+        let second = future_to_promise(context, shared.clone()).unwrap();
+        run_until_stalled(&runtime);
+        rooted!(in(context) let value = Int32Value(9));
+        shared.resolve(context, value.handle()).unwrap();
+        let value = block_on(&runtime, first).unwrap().unwrap();
+        assert_eq!(value.get().to_int32(), 9);
+        let value = block_on(&runtime, second).unwrap().unwrap();
+        assert_eq!(value.get().to_int32(), 9);
+
+        // Futures woken from other threads are waited for while they hold a
+        // wake source.
+        let source = WakeSource::new();
+        let (sender, receiver) = mpsc::channel::<Waker>();
+        let waking = thread::spawn(move || {
+            let waker = receiver.recv().unwrap();
+            thread::sleep(Duration::from_millis(10));
+            waker.wake();
+            drop(source);
+        });
+        let mut sent = false;
+        let output = block_on(
+            &runtime,
+            future::poll_fn(|cx| {
+                if sent {
+                    return Poll::Ready(5);
+                }
+                sender.send(cx.waker().clone()).unwrap();
+                sent = true;
+                Poll::Pending
+            }),
+        );
+        assert_eq!(output, Ok(5));
+        waking.join().unwrap();
+
+        // Promises that nothing will settle can't be waited for.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "new Promise(() => {})",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        rooted!(in(context) let object = rval.to_object());
+        let promise = JSPromise::from_object(object.handle()).unwrap();
+        assert_eq!(block_on(&runtime, promise).err(), Some(Stalled));
+
+        rooted!(in(context) let object = ptr::null_mut::<mozjs::jsapi::JSObject>());
+        assert!(JSPromise::from_object(object.handle()).is_none());
+    }
+}
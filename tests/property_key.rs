@@ -0,0 +1,103 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::jsapi::{jsid, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsapi::{JSITER_OWNONLY, JSITER_SYMBOLS};
+use mozjs::jsval::UndefinedValue;
+use mozjs::property_key::{interned_id, PropertyKey};
+use mozjs::rust::wrappers::{GetPropertyKeys, JS_GetPropertyById};
+use mozjs::rust::{IdVector, JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn property_key() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "({ 1: 'one', name: 'two', 3000000000: 'three', [Symbol('tag')]: 'four' })",
+                "test.js",
+                0,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        rooted!(in(context) let object = rval.to_object());
+
+        let mut ids = IdVector::new(context);
+        assert!(GetPropertyKeys(
+            context,
+            object.handle(),
+            JSITER_OWNONLY | JSITER_SYMBOLS,
+            ids.handle_mut(),
+        ));
+        let keys: Vec<PropertyKey> = ids.keys(context).collect();
+        let names: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        assert_eq!(names, ["1", "3000000000", "name", "Symbol(tag)"]);
+        match keys[0] {
+            PropertyKey::Index(1) => {}
+            ref key => panic!("unexpected key {:?}", key),
+        }
+        match keys[3] {
+            PropertyKey::Symbol(ref symbol) => assert_eq!(symbol.description(), Some("tag")),
+            ref key => panic!("unexpected key {:?}", key),
+        }
+
+        // Keys convert back to ids that find the same properties.
+        let get = |key: &PropertyKey| -> String {
+            rooted!(in(context) let mut id: jsid);
+            key.to_id(context, id.handle_mut()).unwrap();
+            rooted!(in(context) let mut value = UndefinedValue());
+            assert!(JS_GetPropertyById(
+                context,
+                object.handle(),
+                id.handle(),
+                value.handle_mut(),
+            ));
+            match String::from_jsval(context, value.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        let values: Vec<String> = keys.iter().map(&get).collect();
+        assert_eq!(values, ["one", "three", "two", "four"]);
+        assert_eq!(get(&PropertyKey::from(3000000000)), "three");
+        assert_eq!(get(&PropertyKey::from("name")), "two");
+
+        // Interned ids are cached.
+        rooted!(in(context) let mut first: jsid);
+        rooted!(in(context) let mut second: jsid);
+        interned_id(context, "name", first.handle_mut()).unwrap();
+        interned_id(context, "name", second.handle_mut()).unwrap();
+        assert_eq!(first.get().asBits_, second.get().asBits_);
+        assert_eq!(
+            PropertyKey::from_id(context, first.handle()).to_string(),
+            "name"
+        );
+        assert_eq!(
+            PropertyKey::from_id(context, second.handle()).to_string(),
+            "name"
+        );
+    }
+}
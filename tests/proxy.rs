@@ -0,0 +1,260 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::cell::Cell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::rc::Rc;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::error::JsError;
+use mozjs::jsapi::{jsid, ObjectOpResult, OnNewGlobalHookOption, JS_GC};
+use mozjs::jsapi::{GCReason, JSAutoRealm, JSContext, JSObject, JSTracer, JS_NewGlobalObject};
+use mozjs::jsval::{Int32Value, ObjectValue, UndefinedValue};
+use mozjs::property_key::PropertyKey;
+use mozjs::proxy::{get_proxy_target, new_proxy, PropertyKeysOut, ProxyHandler};
+use mozjs::rust::wrappers::JS_DefineProperty;
+use mozjs::rust::{HandleId, HandleObject, HandleValue, MutableHandleValue};
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, Trace, SIMPLE_GLOBAL_CLASS};
+
+/// Forwards everything to the target.
+struct Transparent;
+
+unsafe impl Trace for Transparent {
+    unsafe fn trace(&self, _: *mut JSTracer) {}
+}
+
+impl ProxyHandler for Transparent {}
+
+/// Answers every property get with the number of gets so far, and ignores
+/// writes.
+struct Counting(Rc<Cell<i32>>);
+
+unsafe impl Trace for Counting {
+    unsafe fn trace(&self, _: *mut JSTracer) {}
+}
+
+impl Drop for Counting {
+    fn drop(&mut self) {
+        self.0.set(-1);
+    }
+}
+
+impl ProxyHandler for Counting {
+    unsafe fn get(
+        &self,
+        _cx: *mut JSContext,
+        _proxy: HandleObject,
+        _receiver: HandleValue,
+        _id: HandleId,
+        mut vp: MutableHandleValue,
+    ) -> Result<(), JsError> {
+        self.0.set(self.0.get() + 1);
+        vp.set(Int32Value(self.0.get()));
+        Ok(())
+    }
+
+    unsafe fn set(
+        &self,
+        _cx: *mut JSContext,
+        _proxy: HandleObject,
+        _id: HandleId,
+        _v: HandleValue,
+        _receiver: HandleValue,
+        result: &mut ObjectOpResult,
+    ) -> Result<(), JsError> {
+        result.succeed();
+        Ok(())
+    }
+
+    unsafe fn has(
+        &self,
+        _cx: *mut JSContext,
+        _proxy: HandleObject,
+        _id: HandleId,
+    ) -> Result<bool, JsError> {
+        Err(JsError::TypeError("has is not supported".to_owned()))
+    }
+
+    unsafe fn has_own(
+        &self,
+        _cx: *mut JSContext,
+        _proxy: HandleObject,
+        _id: HandleId,
+    ) -> Result<bool, JsError> {
+        Ok(true)
+    }
+
+    unsafe fn own_property_keys(
+        &self,
+        cx: *mut JSContext,
+        proxy: HandleObject,
+        props: &mut PropertyKeysOut,
+    ) -> Result<(), JsError> {
+        rooted!(in(cx) let target = get_proxy_target(proxy.get()));
+        props.append_own_keys(cx, target.handle())?;
+        rooted!(in(cx) let mut id: jsid);
+        PropertyKey::String("count".to_owned())
+            .to_id(cx, id.handle_mut())
+            .map_err(|()| JsError::Pending)?;
+        props.append(id.handle())
+    }
+}
+
+/// Panics on every property get.
+struct Panicking;
+
+unsafe impl Trace for Panicking {
+    unsafe fn trace(&self, _: *mut JSTracer) {}
+}
+
+impl ProxyHandler for Panicking {
+    unsafe fn get(
+        &self,
+        _cx: *mut JSContext,
+        _proxy: HandleObject,
+        _receiver: HandleValue,
+        _id: HandleId,
+        _vp: MutableHandleValue,
+    ) -> Result<(), JsError> {
+        panic!("get was called");
+    }
+}
+
+#[test]
+fn proxy() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        let define = |name: &str, object: *mut JSObject| {
+            rooted!(in(context) let value = ObjectValue(object));
+            let name = format!("{}\0", name);
+            assert!(JS_DefineProperty(
+                context,
+                global.handle(),
+                name.as_ptr() as *const _,
+                value.handle(),
+                0,
+            ));
+        };
+        let object = |script: &str| -> *mut JSObject {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            rval.to_object()
+        };
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        let error = |script: &str| -> String {
+            eval(&format!(
+                "try {{ {}; 'no error' }} catch (e) {{ e.name + ': ' + e.message }}",
+                script
+            ))
+        };
+
+        // Default traps forward to the target.
+        rooted!(in(context) let target = object("({ a: 1 })"));
+        rooted!(in(context) let proto = object("({ inherited: 'yes' })"));
+        rooted!(in(context) let proxy =
+            new_proxy(context, Transparent, target.handle(), proto.handle()).unwrap());
+        assert_eq!(get_proxy_target(proxy.get()), target.get());
+        define("transparent", proxy.get());
+        define("target", target.get());
+        assert_eq!(eval("transparent.b = 2; String(target.b)"), "2");
+        assert_eq!(eval("Object.keys(transparent).join()"), "a,b");
+        assert_eq!(eval("String('a' in transparent)"), "true");
+        assert_eq!(eval("delete transparent.a; String(target.a)"), "undefined");
+        assert_eq!(eval("transparent.inherited"), "yes");
+        assert_eq!(
+            eval("String(Object.prototype.hasOwnProperty.call(transparent, 'b'))"),
+            "true"
+        );
+        assert_eq!(
+            error("Function.prototype.toString.call(transparent)"),
+            "TypeError: Function.prototype.toString called on incompatible object"
+        );
+
+        rooted!(in(context) let function = object("(function (x) { return x * 2; })"));
+        rooted!(in(context) let callable =
+            new_proxy(context, Transparent, function.handle(), HandleObject::null()).unwrap());
+        define("callable", callable.get());
+        assert_eq!(eval("typeof callable"), "function");
+        assert_eq!(eval("String(callable(21))"), "42");
+        assert_eq!(
+            eval("Function.prototype.toString.call(callable)"),
+            "function () {\n    [native code]\n}"
+        );
+
+        // Overridden traps run the Rust handler.
+        let gets = Rc::new(Cell::new(0));
+        {
+            rooted!(in(context) let counting = new_proxy(
+                context,
+                Counting(gets.clone()),
+                target.handle(),
+                HandleObject::null(),
+            )
+            .unwrap());
+            define("counting", counting.get());
+        }
+        assert_eq!(eval("counting.x; String(counting.y)"), "2");
+        assert_eq!(
+            eval("counting.z = 'ignored'; String(target.z)"),
+            "undefined"
+        );
+        assert_eq!(error("'x' in counting"), "TypeError: has is not supported");
+        assert_eq!(eval("Reflect.ownKeys(counting).join()"), "b,count");
+        assert_eq!(
+            eval("String(Object.prototype.hasOwnProperty.call(counting, 'missing'))"),
+            "true"
+        );
+        assert_eq!(gets.get(), 2);
+
+        // The handler is dropped once the proxy is collected.
+        eval("delete counting; ''");
+        JS_GC(context, GCReason::API);
+        assert_eq!(gets.get(), -1);
+
+        // Panics propagate out of the trap.
+        rooted!(in(context) let panicking =
+            new_proxy(context, Panicking, target.handle(), HandleObject::null()).unwrap());
+        define("panicking", panicking.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _ = runtime.evaluate_script(
+                global.handle(),
+                "panicking.x",
+                "test.js",
+                0,
+                rval.handle_mut(),
+            );
+        }));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::collections::{BTreeMap, HashMap};
+use std::ptr;
+
+use mozjs::conversions::{
+    ConversionBehavior, ConversionResult, FromJSValConvertible, ToJSValConvertible,
+};
+use mozjs::jsapi::{
+    InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption,
+};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn record_conversion() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        let mut orig_map = HashMap::new();
+        orig_map.insert("a".to_owned(), 1);
+        orig_map.insert("\u{e9}t\u{e9}".to_owned(), 2);
+        orig_map.to_jsval(context, rval.handle_mut());
+        let converted =
+            HashMap::<String, i32>::from_jsval(context, rval.handle(), ConversionBehavior::Default)
+                .unwrap();
+        assert_eq!(&orig_map, converted.get_success_value().unwrap());
+
+        // Only own enumerable properties are converted, with index keys as
+        // strings.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "let o = Object.create({ inherited: 'x' }); \
+                 Object.defineProperty(o, 'hidden', { value: 'y' }); \
+                 o.b = 'b'; o[1] = 'one'; o",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        let converted = BTreeMap::<String, String>::from_jsval(context, rval.handle(), ())
+            .unwrap()
+            .get_success_value()
+            .cloned()
+            .unwrap();
+        let keys: Vec<&str> = converted.keys().map(|k| &**k).collect();
+        assert_eq!(keys, ["1", "b"]);
+        assert_eq!(converted["1"], "one");
+
+        assert!(runtime
+            .evaluate_script(global.handle(), "42", "test", 1, rval.handle_mut())
+            .is_ok());
+        match BTreeMap::<String, String>::from_jsval(context, rval.handle(), ()).unwrap() {
+            ConversionResult::Failure(_) => {}
+            ConversionResult::Success(_) => panic!("a number was converted to a record"),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::jsapi::{JSGCParamKey, JS_GetGCParameter, JS_NewGlobalObject, OnNewGlobalHookOption};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::SIMPLE_GLOBAL_CLASS;
+use mozjs::rust::{JSEngine, RealmOptions, RuntimeBuildError, RuntimeBuilder, StackQuota};
+
+#[test]
+fn runtime_builder() {
+    let engine = JSEngine::init().unwrap();
+
+    // Invalid configurations are rejected before a context is created.
+    let error = RuntimeBuilder::new(engine.handle())
+        .heap_size(u32::MAX)
+        .build()
+        .err();
+    assert_eq!(error, Some(RuntimeBuildError::HeapSizeOverflow));
+    let error = RuntimeBuilder::new(engine.handle())
+        .stack_quota(StackQuota {
+            max: 1024,
+            system_code_buffer: usize::MAX,
+            trusted_script_buffer: 1,
+        })
+        .build()
+        .err();
+    assert_eq!(error, Some(RuntimeBuildError::InvalidStackQuota));
+
+    let runtime = RuntimeBuilder::new(engine.handle())
+        .heap_size(8 * 1024 * 1024)
+        .max_bytes(64 * 1024 * 1024)
+        .nursery_bytes(1024 * 1024)
+        .stack_quota(StackQuota {
+            max: 256 * 1024,
+            system_code_buffer: 10 * 1024,
+            trusted_script_buffer: 32 * 1024,
+        })
+        .warning_reporter(None)
+        .job_queue(false)
+        .build()
+        .unwrap();
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        assert_eq!(
+            JS_GetGCParameter(context, JSGCParamKey::JSGC_MAX_BYTES),
+            64 * 1024 * 1024
+        );
+        assert_eq!(
+            JS_GetGCParameter(context, JSGCParamKey::JSGC_MAX_NURSERY_BYTES),
+            1024 * 1024
+        );
+
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+        assert!(runtime
+            .evaluate_script(global.handle(), "1 + 1", "test", 1, rval.handle_mut())
+            .is_ok());
+        assert_eq!(rval.get().to_int32(), 2);
+
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "function f() { f.apply() } f()",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_err());
+
+        // Without the built-in job queue, checkpoints do nothing.
+        runtime.run_jobs();
+    }
+}
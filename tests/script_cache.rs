@@ -0,0 +1,80 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::jsapi::{JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption, TranscodeResult};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{set_build_id, CompileOptionsWrapper, JSEngine, RealmOptions, Runtime};
+use mozjs::rust::{Script, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn script_cache() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        let script = runtime
+            .compile_script(
+                global.handle(),
+                "function square(x) { return x * x; } square(7)",
+                "bootstrap.js",
+                1,
+            )
+            .unwrap();
+        runtime
+            .execute_script(global.handle(), &script, rval.handle_mut())
+            .unwrap();
+        assert_eq!(rval.get().to_int32(), 49);
+
+        let bytecode = script.encode().unwrap();
+        assert!(!bytecode.is_empty());
+
+        rooted!(in(context) let other = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        {
+            let _ac = JSAutoRealm::new(context, other.get());
+            let options = CompileOptionsWrapper::new(context, "bootstrap.js", 1);
+            let decoded = Script::decode(context, &bytecode, &options).unwrap();
+            rval.set(UndefinedValue());
+            runtime
+                .execute_script(other.handle(), &decoded, rval.handle_mut())
+                .unwrap();
+            assert_eq!(rval.get().to_int32(), 49);
+
+            // Garbage and bytecode from other builds are rejected.
+            assert!(Script::decode(context, &[1, 2, 3, 4], &options).is_err());
+            set_build_id(b"another build");
+            assert_eq!(
+                Script::decode(context, &bytecode, &options).err(),
+                Some(TranscodeResult::Failure_BadBuildId)
+            );
+        }
+
+        assert!(runtime
+            .compile_script(global.handle(), "let = ;", "broken.js", 1)
+            .is_err());
+    }
+}
@@ -0,0 +1,159 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+#[macro_use]
+extern crate serde;
+
+use std::collections::BTreeMap;
+use std::ptr;
+
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsapi::{InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, JS_SetProperty};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+use mozjs::serde::{from_jsval, to_jsval, PathSegment};
+use mozjs::typedarray::Uint8Array;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect { width: u32, height: u32 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    offset: i64,
+    name: String,
+    tags: Vec<String>,
+    shapes: Vec<Shape>,
+    parent: Option<Box<Record>>,
+    counts: BTreeMap<String, u8>,
+    #[serde(with = "bytes")]
+    data: Vec<u8>,
+}
+
+mod bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Vec::deserialize(deserializer)
+    }
+}
+
+#[test]
+fn serde() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        // Round trip through JS values.
+        let mut counts = BTreeMap::new();
+        counts.insert("a".to_owned(), 1);
+        counts.insert("b".to_owned(), 2);
+        let record = Record {
+            id: u64::MAX,
+            offset: -3,
+            name: "root".to_owned(),
+            tags: vec!["x".to_owned(), "y".to_owned()],
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Rect {
+                    width: 2,
+                    height: 3,
+                },
+            ],
+            parent: None,
+            counts,
+            data: vec![1, 2, 3],
+        };
+        rooted!(in(context) let mut rval = UndefinedValue());
+        to_jsval(context, &record, rval.handle_mut()).unwrap();
+        assert_eq!(
+            from_jsval::<Record>(context, rval.handle()).unwrap(),
+            record
+        );
+
+        // Inspect the representation from JS.
+        rooted!(in(context) let mut check = UndefinedValue());
+        assert!(JS_SetProperty(
+            context,
+            global.handle().into(),
+            b"record\0".as_ptr() as *const _,
+            rval.handle().into(),
+        ));
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "typeof record.id == 'bigint' && record.id == 2n ** 64n - 1n && \
+                 record.offset === -3 && record.shapes[0] === 'Empty' && \
+                 record.shapes[1].Circle === 1.5 && record.shapes[2].Rect.height === 3 && \
+                 record.parent === null && record.counts.b === 2 && \
+                 record.data instanceof Uint8Array",
+                "test",
+                1,
+                check.handle_mut(),
+            )
+            .is_ok());
+        assert!(check.to_boolean());
+
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "record.data",
+                "test",
+                1,
+                check.handle_mut()
+            )
+            .is_ok());
+        rooted!(in(context) let data = check.to_object());
+        assert_eq!(
+            Uint8Array::from(data.get()).unwrap().to_vec(),
+            vec![1, 2, 3]
+        );
+
+        // Errors name the member that failed to convert.
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "record.shapes[2].Rect.width = -1; record",
+                "test",
+                1,
+                rval.handle_mut(),
+            )
+            .is_ok());
+        let error = from_jsval::<Record>(context, rval.handle()).unwrap_err();
+        assert_eq!(
+            error.path(),
+            &[
+                PathSegment::Member("shapes".to_owned()),
+                PathSegment::Index(2),
+                PathSegment::Member("Rect".to_owned()),
+                PathSegment::Member("width".to_owned()),
+            ]
+        );
+        assert!(error.to_string().starts_with("shapes[2].Rect.width: "));
+    }
+}
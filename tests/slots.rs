@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::os::raw::{c_char, c_void};
+use std::panic::catch_unwind;
+use std::ptr;
+
+use mozjs::conversions::jsstr_to_string;
+use mozjs::jsapi::JS_GC;
+use mozjs::jsapi::{GCReason, JSAutoRealm, JSClass, JSObject, JSString, JS_NewGlobalObject};
+use mozjs::jsapi::{JS_NewObject, JS_NewPlainObject, JS_NewStringCopyZ, OnNewGlobalHookOption};
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+use mozjs::slots::{reserved_slots_flags, PrivateSlot, Slot, SlotLayout};
+use mozjs::JSCLASS_RESERVED_SLOTS_MASK;
+
+static POINT_CLASS: JSClass = JSClass {
+    name: b"Point\0" as *const u8 as *const c_char,
+    flags: reserved_slots_flags(5),
+    cOps: ptr::null(),
+    spec: ptr::null(),
+    ext: ptr::null(),
+    oOps: ptr::null(),
+};
+
+#[test]
+fn slots() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        let layout = SlotLayout::new(&POINT_CLASS, 5);
+        let x: Slot<f64> = layout.slot(0);
+        let visible: Slot<bool> = layout.slot(1);
+        let parent: Slot<*mut JSObject> = layout.slot(2);
+        let label: Slot<*mut JSString> = layout.slot(3);
+        let data: PrivateSlot = layout.private_slot(4);
+
+        rooted!(in(context) let point = JS_NewObject(context, &POINT_CLASS));
+        assert!(layout.is_instance(point.get()));
+        assert_eq!(x.get(point.get()), None);
+
+        let value = 7u8;
+        x.set(point.get(), 1.5).unwrap();
+        visible.set(point.get(), true).unwrap();
+        parent.set(point.get(), global.get()).unwrap();
+        label
+            .set(
+                point.get(),
+                JS_NewStringCopyZ(context, b"origin\0".as_ptr() as *const _),
+            )
+            .unwrap();
+        data.set(point.get(), &value as *const u8 as *const c_void)
+            .unwrap();
+
+        // Stored GC things are traced by the object.
+        JS_GC(context, GCReason::API);
+        assert_eq!(x.get(point.get()), Some(1.5));
+        assert_eq!(visible.get(point.get()), Some(true));
+        assert_eq!(parent.get(point.get()), Some(global.get()));
+        assert_eq!(
+            jsstr_to_string(context, label.get(point.get()).unwrap()),
+            "origin"
+        );
+        assert_eq!(*(data.get(point.get()).unwrap() as *const u8), 7);
+
+        // Slots hold one type each, and can be cleared.
+        let x_as_bool: Slot<bool> = layout.slot(0);
+        assert_eq!(x_as_bool.get(point.get()), None);
+        x.clear(point.get()).unwrap();
+        assert_eq!(x.get(point.get()), None);
+
+        // Objects of other classes are rejected.
+        rooted!(in(context) let plain = JS_NewPlainObject(context));
+        assert!(!layout.is_instance(plain.get()));
+        assert_eq!(x.get(plain.get()), None);
+        assert!(x.set(plain.get(), 2.0).is_err());
+
+        // Layouts must fit the class.
+        assert!(catch_unwind(|| SlotLayout::new(&POINT_CLASS, 6)).is_err());
+        assert!(
+            catch_unwind(|| SlotLayout::new(&POINT_CLASS, JSCLASS_RESERVED_SLOTS_MASK + 1))
+                .is_err()
+        );
+        assert!(catch_unwind(|| layout.slot::<f64>(5)).is_err());
+        assert!(catch_unwind(|| layout.private_slot(5)).is_err());
+        assert_eq!(reserved_slots_flags(5), POINT_CLASS.flags);
+    }
+}
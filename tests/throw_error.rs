@@ -0,0 +1,151 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::panic::catch_unwind;
+use std::ptr;
+
+use mozjs::conversions::{ConversionResult, FromJSValConvertible};
+use mozjs::error;
+use mozjs::error::{throw_aggregate_error, throw_eval_error, throw_internal_error};
+use mozjs::error::{throw_range_error, throw_reference_error, throw_syntax_error};
+use mozjs::error::{throw_type_error, throw_uri_error, ErrorBuilder, JSException};
+use mozjs::jsapi::OnNewGlobalHookOption;
+use mozjs::jsapi::{HandleValueArray, JSAutoRealm, JSContext, JSExnType, JS_NewGlobalObject};
+use mozjs::jsval::{Int32Value, UndefinedValue};
+use mozjs::rust::wrappers::JS_DefineProperty;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn throw_error() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+
+        let eval = |script: &str| -> String {
+            rooted!(in(context) let mut rval = UndefinedValue());
+            assert!(runtime
+                .evaluate_script(global.handle(), script, "test.js", 0, rval.handle_mut())
+                .is_ok());
+            match String::from_jsval(context, rval.handle(), ()).unwrap() {
+                ConversionResult::Success(s) => s,
+                ConversionResult::Failure(e) => panic!("{}", e),
+            }
+        };
+        // Takes the pending exception and stores it in the global `e`.
+        let take = || -> JSException {
+            let exception = JSException::take(context).expect("no pending exception");
+            assert!(JS_DefineProperty(
+                context,
+                global.handle(),
+                b"e\0".as_ptr() as *const _,
+                exception.value(),
+                0,
+            ));
+            exception
+        };
+
+        let throws: [(unsafe fn(*mut JSContext, &str), JSExnType, &str); 7] = [
+            (throw_type_error, JSExnType::JSEXN_TYPEERR, "TypeError"),
+            (throw_range_error, JSExnType::JSEXN_RANGEERR, "RangeError"),
+            (
+                throw_internal_error,
+                JSExnType::JSEXN_INTERNALERR,
+                "InternalError",
+            ),
+            (
+                throw_syntax_error,
+                JSExnType::JSEXN_SYNTAXERR,
+                "SyntaxError",
+            ),
+            (
+                throw_reference_error,
+                JSExnType::JSEXN_REFERENCEERR,
+                "ReferenceError",
+            ),
+            (throw_eval_error, JSExnType::JSEXN_EVALERR, "EvalError"),
+            (throw_uri_error, JSExnType::JSEXN_URIERR, "URIError"),
+        ];
+        for &(throw, exn_type, name) in &throws {
+            throw(context, "message");
+            let exception = take();
+            assert_eq!(exception.exn_type, Some(exn_type));
+            assert_eq!(exception.message, "message");
+            assert_eq!(eval(&format!("String(e instanceof {})", name)), "true");
+            assert_eq!(
+                eval("e.name + ': ' + e.message"),
+                format!("{}: message", name)
+            );
+        }
+
+        error::throw_error(context, JSExnType::JSEXN_ERR, "plain");
+        assert_eq!(take().exn_type, Some(JSExnType::JSEXN_ERR));
+        assert_eq!(
+            eval("e.constructor.name + ': ' + e.message"),
+            "Error: plain"
+        );
+
+        // Warnings and the other variants of `JSExnType` are not errors.
+        let result = catch_unwind(|| error::throw_error(context, JSExnType::JSEXN_WARN, "warning"));
+        assert!(result.is_err());
+        assert!(JSException::take(context).is_none());
+
+        // Messages may contain NUL characters.
+        throw_type_error(context, "a\0b");
+        take();
+        assert_eq!(eval("String(e.message.length)"), "3");
+
+        let errors = [Int32Value(1), Int32Value(2)];
+        throw_aggregate_error(
+            context,
+            &HandleValueArray::from_rooted_slice(&errors),
+            "several",
+        );
+        assert_eq!(take().exn_type, Some(JSExnType::JSEXN_AGGREGATEERR));
+        assert_eq!(eval("e.message + ': ' + e.errors.join()"), "several: 1,2");
+
+        // Subclasses and DOMException-like errors.
+        rooted!(in(context) let mut proto = UndefinedValue());
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "class CustomError extends Error {}; CustomError.prototype",
+                "test.js",
+                0,
+                proto.handle_mut(),
+            )
+            .is_ok());
+        rooted!(in(context) let proto = proto.to_object());
+        rooted!(in(context) let code = Int32Value(8));
+        ErrorBuilder::new(JSExnType::JSEXN_ERR, "not found")
+            .prototype(proto.handle())
+            .name("NotFoundError")
+            .property("code", code.handle())
+            .throw(context);
+        take();
+        assert_eq!(
+            eval("String(e instanceof CustomError && e instanceof Error)"),
+            "true"
+        );
+        assert_eq!(
+            eval("e.name + ': ' + e.message + ' (' + e.code + ')'"),
+            "NotFoundError: not found (8)"
+        );
+        assert_eq!(eval("Object.keys(e).join()"), "");
+    }
+}
@@ -0,0 +1,96 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[macro_use]
+extern crate mozjs;
+
+use std::ptr;
+
+use mozjs::conversions::{
+    ConversionBehavior, ConversionResult, FromJSValConvertible, ToJSValConvertible,
+};
+use mozjs::error::JSException;
+use mozjs::jsapi::{
+    InitRealmStandardClasses, JSAutoRealm, JS_NewGlobalObject, OnNewGlobalHookOption,
+};
+use mozjs::jsval::UndefinedValue;
+use mozjs::rust::{JSEngine, RealmOptions, Runtime, SIMPLE_GLOBAL_CLASS};
+
+#[test]
+fn tuple_conversion() {
+    let engine = JSEngine::init().unwrap();
+    let runtime = Runtime::new(engine.handle());
+    let context = runtime.cx();
+
+    let h_option = OnNewGlobalHookOption::FireOnNewGlobalHook;
+    let c_option = RealmOptions::default();
+
+    unsafe {
+        rooted!(in(context) let global = JS_NewGlobalObject(
+            context,
+            &SIMPLE_GLOBAL_CLASS,
+            ptr::null_mut(),
+            h_option,
+            &*c_option,
+        ));
+        let _ac = JSAutoRealm::new(context, global.get());
+        assert!(InitRealmStandardClasses(context));
+
+        rooted!(in(context) let mut rval = UndefinedValue());
+
+        let orig_tuple = ("pi".to_owned(), 3.14f64, true);
+        orig_tuple.to_jsval(context, rval.handle_mut());
+        let converted =
+            <(String, f64, bool)>::from_jsval(context, rval.handle(), ((), (), ())).unwrap();
+        assert_eq!(&orig_tuple, converted.get_success_value().unwrap());
+
+        let orig_array = [1u8, 2, 3, 4];
+        orig_array.to_jsval(context, rval.handle_mut());
+        let converted =
+            <[u8; 4]>::from_jsval(context, rval.handle(), ConversionBehavior::Default).unwrap();
+        assert_eq!(&orig_array, converted.get_success_value().unwrap());
+
+        // The length must match exactly.
+        match <[u8; 3]>::from_jsval(context, rval.handle(), ConversionBehavior::Default).unwrap() {
+            ConversionResult::Failure(e) => assert!(e.contains("length 3")),
+            ConversionResult::Success(_) => panic!("array of the wrong length was accepted"),
+        }
+        match <(u8, u8)>::from_jsval(context, rval.handle(), Default::default()).unwrap() {
+            ConversionResult::Failure(e) => assert!(e.contains("length 4")),
+            ConversionResult::Success(_) => panic!("array of the wrong length was accepted"),
+        }
+
+        assert!(runtime
+            .evaluate_script(
+                global.handle(),
+                "({ 0: 'a', 1: 2, length: 2 })",
+                "test",
+                1,
+                rval.handle_mut()
+            )
+            .is_ok());
+        match <(String, i32)>::from_jsval(context, rval.handle(), ((), Default::default())).unwrap()
+        {
+            ConversionResult::Failure(_) => {}
+            ConversionResult::Success(_) => panic!("array-like object was accepted"),
+        }
+
+        // Elements that fail to convert throw a TypeError naming their index.
+        assert!(runtime
+            .evaluate_script(global.handle(), "[[1], 2]", "test", 1, rval.handle_mut())
+            .is_ok());
+        let behavior = ConversionBehavior::Default;
+        assert!(<[[u8; 1]; 2]>::from_jsval(context, rval.handle(), behavior.clone()).is_err());
+        let exception = JSException::take(context).unwrap();
+        assert_eq!(exception.message, "Element 1: Value is not an object");
+        assert!(<(Vec<u8>, Vec<u8>)>::from_jsval(
+            context,
+            rval.handle(),
+            (behavior.clone(), behavior)
+        )
+        .is_err());
+        let exception = JSException::take(context).unwrap();
+        assert_eq!(exception.message, "Element 1: Value is not an object");
+    }
+}